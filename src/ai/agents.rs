@@ -1,4 +1,4 @@
-use super::client::GeminiClient;
+use super::client::{estimate_tokens, extract_json, GeminiClient, LlmClient};
 use super::prompts;
 use super::schema_utils;
 use crate::core::dsl::{AppDefinition, AppProgram, LogicStep};
@@ -8,16 +8,32 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 pub struct AgentSwarm {
-    client: GeminiClient,
+    client: Box<dyn LlmClient>,
+    logic_retries: usize,
 }
 
-#[derive(Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct TestCase {
     pub name: String,
     pub input: Value,
     pub expected_output_keys: Vec<String>,
 }
 
+/// A step array the fixer already produced, with the failure it caused.
+#[derive(Debug, Clone, Serialize)]
+pub struct FixAttempt {
+    pub steps: Vec<LogicStep>,
+    pub error: String,
+}
+
+/// Approximate prompt size of a build, from `AgentSwarm::estimate_build_cost`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CostEstimate {
+    /// `(stage, estimated prompt tokens)` in pipeline order.
+    pub stages: Vec<(String, usize)>,
+    pub prompt_tokens: usize,
+}
+
 #[derive(Serialize, Deserialize, schemars::JsonSchema)]
 pub struct AppDefinitionResponse {
     pub name: String,
@@ -27,22 +43,50 @@ pub struct AppDefinitionResponse {
 }
 
 impl AgentSwarm {
-    pub fn new() -> Self {
-        Self { client: GeminiClient::new() }
+    pub fn new() -> Result<Self, MetaError> {
+        Ok(Self::with_client(Box::new(GeminiClient::new()?)))
     }
 
-    pub async fn define_app(&self, user_request: &str) -> Result<AppDefinition, MetaError> {
-        let raw_schema = schema_for!(AppDefinitionResponse);
-        let raw_schema_text = serde_json::to_string_pretty(&raw_schema).unwrap();
-        let clean_schema_val = schema_utils::clean_schema(raw_schema).map_err(MetaError::JsonError)?;
+    pub fn with_client(client: Box<dyn LlmClient>) -> Self {
+        Self { client, logic_retries: 3 }
+    }
 
-        let system_prompt = format!("{}\n\nREQUIRED OUTPUT SCHEMA:\n{}", prompts::ARCHITECT_PROMPT, raw_schema_text);
+    /// How many times `write_logic` re-prompts after an unparseable response.
+    pub fn with_logic_retries(mut self, retries: usize) -> Self {
+        self.logic_retries = retries.max(1);
+        self
+    }
 
-        let resp = self.client.generate(&system_prompt, user_request, Some(clean_schema_val), "Architecture").await?;
-        
-        let dto: AppDefinitionResponse = serde_json::from_str(&resp).map_err(|e| {
-            MetaError::ValidationFailed(format!("Architect parse failed: {}", e))
-        })?;
+    /// Rough prompt-token count for one build of `user_request`: the
+    /// Architecture, Development and QA prompts (system + user). Schemas the
+    /// architect hasn't produced yet are approximated by the request text, and
+    /// fixer calls and response tokens are not included.
+    pub fn estimate_build_cost(&self, user_request: &str) -> CostEstimate {
+        let placeholder = AppDefinition {
+            name: String::new(),
+            description: String::new(),
+            input_schema: Value::String(user_request.to_string()),
+            output_schema: Value::String(user_request.to_string()),
+        };
+        let stages = vec![
+            ("Architecture".to_string(), estimate_tokens(&architect_system_prompt()) + estimate_tokens(user_request)),
+            (
+                "Development".to_string(),
+                estimate_tokens(&developer_system_prompt()) + estimate_tokens(&developer_user_prompt(&placeholder)),
+            ),
+            ("QA".to_string(), estimate_tokens(&qa_system_prompt()) + estimate_tokens(&qa_user_prompt(&placeholder))),
+        ];
+        let prompt_tokens = stages.iter().map(|(_, tokens)| tokens).sum();
+        CostEstimate { stages, prompt_tokens }
+    }
+
+    pub async fn define_app(&self, user_request: &str) -> Result<AppDefinition, MetaError> {
+        let clean_schema_val = schema_utils::clean_schema(schema_for!(AppDefinitionResponse)).map_err(MetaError::JsonError)?;
+        let system_prompt = architect_system_prompt();
+
+        let dto: AppDefinitionResponse = self.client
+            .generate_typed(&system_prompt, user_request, Some(clean_schema_val), "Architecture")
+            .await?;
 
         let input_schema = parse_json_string(&dto.input_schema_json, "input_schema")?;
         let output_schema = parse_json_string(&dto.output_schema_json, "output_schema")?;
@@ -57,65 +101,11 @@ impl AgentSwarm {
 
     pub async fn write_logic(&self, definition: &AppDefinition) -> Result<AppProgram, MetaError> {
         // We use the raw schema text for the PROMPT, but pass None for the API schema.
-        let raw_schema = schema_for!(Vec<LogicStep>);
-        let raw_schema_text = serde_json::to_string_pretty(&raw_schema).unwrap();
-
-        let system = format!(
-            r#"
-            You are a Backend Logic Developer.
-            
-            GOAL: Write a JSON Logic Program that transforms Input to Output.
-            
-            STRICT SCHEMA DOCUMENTATION:
-            {}
-            
-            IMPORTANT EXAMPLES:
-            
-            1. Math Operation:
-            {{
-              "id": "calc_tax",
-              "description": "Calculate tax",
-              "operation": {{
-                "op": "multiply",
-                "a": "/revenue", 
-                "b": "/tax_rate"
-              }},
-              "output_path": "/tax_amount"
-            }}
-
-            2. Format String (CRITICAL):
-            {{
-              "id": "summary",
-              "description": "Make summary",
-              "operation": {{
-                "op": "format_string",
-                "template": "Project {{name}} made ${{profit}}.",
-                "variables": [
-                   {{ "key": "name", "path": "/project/name" }},
-                   {{ "key": "profit", "path": "/project/profit" }}
-                ]
-              }},
-              "output_path": "/summary"
-            }}
-            
-            INSTRUCTIONS:
-            1. Return ONLY the JSON array of steps.
-            2. Use the 'op' field to define the operation type.
-            3. MATH OPS: Operands 'a' and 'b' MUST BE PATH STRINGS (e.g., "/revenue"). To use a number, use 'constant' op first.
-            4. FORMAT_STRING: 'variables' must be an ARRAY OF OBJECTS (key/path).
-            "#,
-            raw_schema_text
-        );
-
-        let initial_user_prompt = format!(
-            "App Name: {}\nInput Schema: {}\nOutput Schema: {}\n\nGenerate the logic.",
-            definition.name,
-            serde_json::to_string_pretty(&definition.input_schema).unwrap(),
-            serde_json::to_string_pretty(&definition.output_schema).unwrap()
-        );
+        let system = developer_system_prompt();
+        let initial_user_prompt = developer_user_prompt(definition);
 
         let mut user = initial_user_prompt.clone();
-        let max_retries = 3;
+        let max_retries = self.logic_retries;
 
         for attempt in 1..=max_retries {
             // Passing None for schema to avoid strict mode parsing issues with recursion
@@ -126,6 +116,7 @@ impl AgentSwarm {
                     return Ok(AppProgram {
                         definition: definition.clone(),
                         steps,
+                        examples: Vec::new(),
                     });
                 }
                 Err(e) => {
@@ -150,24 +141,23 @@ impl AgentSwarm {
     }
 
     pub async fn generate_tests(&self, definition: &AppDefinition) -> Result<Vec<TestCase>, MetaError> {
-        let raw_schema = schema_for!(Vec<TestCase>);
-        let raw_schema_text = serde_json::to_string_pretty(&raw_schema).unwrap();
-        let clean_schema_val = schema_utils::clean_schema(raw_schema).map_err(MetaError::JsonError)?;
-
-        let system = format!("{}\n\nREQUIRED SCHEMA:\n{}", prompts::QA_PROMPT, raw_schema_text);
-
-        let user = format!(
-            "Input Schema: {}\nGenerate 3 diverse test cases.",
-            serde_json::to_string_pretty(&definition.input_schema).unwrap()
-        );
-        
-        let resp = self.client.generate(&system, &user, Some(clean_schema_val), "QA").await?;
-        serde_json::from_str(&resp).map_err(|e| {
-            MetaError::ValidationFailed(format!("Tests parse failed: {}", e))
-        })
+        let clean_schema_val = schema_utils::clean_schema(schema_for!(Vec<TestCase>)).map_err(MetaError::JsonError)?;
+        self.client.generate_typed(&qa_system_prompt(), &qa_user_prompt(definition), Some(clean_schema_val), "QA").await
     }
 
     pub async fn fix_program(&self, program: &AppProgram, definition: &AppDefinition, error_log: &str) -> Result<AppProgram, MetaError> {
+        self.fix_program_with_history(program, definition, error_log, &[]).await
+    }
+
+    /// Like `fix_program`, but also shows the fixer earlier attempts (oldest
+    /// first) and their errors so it doesn't reintroduce a bug it already fixed.
+    pub async fn fix_program_with_history(
+        &self,
+        program: &AppProgram,
+        definition: &AppDefinition,
+        error_log: &str,
+        history: &[FixAttempt],
+    ) -> Result<AppProgram, MetaError> {
         let raw_schema = schema_for!(Vec<LogicStep>);
         let raw_schema_text = serde_json::to_string_pretty(&raw_schema).unwrap();
 
@@ -178,23 +168,20 @@ impl AgentSwarm {
         );
 
         let user = format!(
-            "CONTEXT:\nApp Name: {}\nInput Schema: {}\n\nCurrent Steps: {}\n\nRuntime Error: {}\n\n\
+            "CONTEXT:\nApp Name: {}\nInput Schema: {}\n\n{}Current Steps: {}\n\nRuntime Error: {}\n\n\
             INSTRUCTIONS:\n\
             1. Return the FIXED steps array.\n\
             2. 'FormatString': use Array [ {{ \"key\": \"...\", \"path\": \"...\" }} ].\n\
             3. Math Operands: MUST be strings (paths).",
             definition.name,
             serde_json::to_string_pretty(&definition.input_schema).unwrap(),
+            format_history(history),
             serde_json::to_string_pretty(&program.steps).unwrap(),
             error_log
         );
 
         // Passing None for schema
-        let new_steps: Vec<LogicStep> = serde_json::from_str(
-            &self.client.generate(&system, &user, None, "Fixer").await?
-        ).map_err(|e| {
-            MetaError::ValidationFailed(format!("Fixer parse failed: {}", e))
-        })?;
+        let new_steps: Vec<LogicStep> = self.client.generate_typed(&system, &user, None, "Fixer").await?;
         
         let mut new_program = program.clone();
         new_program.steps = new_steps;
@@ -202,36 +189,168 @@ impl AgentSwarm {
     }
 }
 
+fn architect_system_prompt() -> String {
+    let raw_schema_text = serde_json::to_string_pretty(&schema_for!(AppDefinitionResponse)).unwrap();
+    format!("{}\n\nREQUIRED OUTPUT SCHEMA:\n{}", prompts::ARCHITECT_PROMPT, raw_schema_text)
+}
+
+fn developer_system_prompt() -> String {
+    let raw_schema = schema_for!(Vec<LogicStep>);
+    let raw_schema_text = serde_json::to_string_pretty(&raw_schema).unwrap();
+
+    format!(
+        r#"
+            You are a Backend Logic Developer.
+            
+            GOAL: Write a JSON Logic Program that transforms Input to Output.
+            
+            STRICT SCHEMA DOCUMENTATION:
+            {}
+            
+            IMPORTANT EXAMPLES:
+            
+            1. Math Operation:
+            {{
+              "id": "calc_tax",
+              "description": "Calculate tax",
+              "operation": {{
+                "op": "multiply",
+                "a": "/revenue", 
+                "b": "/tax_rate"
+              }},
+              "output_path": "/tax_amount"
+            }}
+
+            2. Format String (CRITICAL):
+            {{
+              "id": "summary",
+              "description": "Make summary",
+              "operation": {{
+                "op": "format_string",
+                "template": "Project {{name}} made ${{profit}}.",
+                "variables": [
+                   {{ "key": "name", "path": "/project/name" }},
+                   {{ "key": "profit", "path": "/project/profit" }}
+                ]
+              }},
+              "output_path": "/summary"
+            }}
+            
+            INSTRUCTIONS:
+            1. Return ONLY the JSON array of steps.
+            2. Use the 'op' field to define the operation type.
+            3. MATH OPS: Operands 'a' and 'b' MUST BE PATH STRINGS (e.g., "/revenue"). To use a number, use 'constant' op first.
+            4. FORMAT_STRING: 'variables' must be an ARRAY OF OBJECTS (key/path).
+            "#,
+        raw_schema_text
+    )
+}
+
+fn developer_user_prompt(definition: &AppDefinition) -> String {
+    format!(
+        "App Name: {}\nInput Schema: {}\nOutput Schema: {}\n\nGenerate the logic.",
+        definition.name,
+        serde_json::to_string_pretty(&definition.input_schema).unwrap(),
+        serde_json::to_string_pretty(&definition.output_schema).unwrap()
+    )
+}
+
+fn qa_system_prompt() -> String {
+    let raw_schema_text = serde_json::to_string_pretty(&schema_for!(Vec<TestCase>)).unwrap();
+    format!("{}\n\nREQUIRED SCHEMA:\n{}", prompts::QA_PROMPT, raw_schema_text)
+}
+
+fn qa_user_prompt(definition: &AppDefinition) -> String {
+    format!(
+        "Input Schema: {}\nGenerate 3 diverse test cases.",
+        serde_json::to_string_pretty(&definition.input_schema).unwrap()
+    )
+}
+
+fn format_history(history: &[FixAttempt]) -> String {
+    if history.is_empty() {
+        return String::new();
+    }
+    let mut out = String::from("PREVIOUS ATTEMPTS (these failed; do not repeat their mistakes):\n");
+    for (i, attempt) in history.iter().enumerate() {
+        out.push_str(&format!(
+            "Attempt {}: {}\nError: {}\n\n",
+            i + 1,
+            serde_json::to_string(&attempt.steps).unwrap(),
+            attempt.error
+        ));
+    }
+    out
+}
+
+/// Parse a schema the architect returned as a JSON string, ignoring any
+/// fences or prose around it (see `extract_json`).
 fn parse_json_string(s: &str, field_name: &str) -> Result<Value, MetaError> {
-    let trimmed = s.trim();
-    let content = trimmed
-        .trim_start_matches("```json")
-        .trim_start_matches("```")
-        .trim_end_matches("```")
-        .trim();
-
-    let sanitized: String = content.chars().map(|c| {
+    let sanitized: String = s.chars().map(|c| {
         if c.is_control() { ' ' } else { c }
     }).collect();
-
-    let json_str = if let Some(start) = sanitized.find('{') {
-        if let Some(end) = sanitized.rfind('}') {
-            if end > start {
-                &sanitized[start..=end]
-            } else {
-                &sanitized
-            }
-        } else {
-            &sanitized
-        }
-    } else {
-        &sanitized
-    };
+    let json_str = extract_json(&sanitized);
 
     serde_json::from_str(json_str).map_err(|e| {
         MetaError::ValidationFailed(format!(
-            "Failed to parse {} string. Error: {}. Content was: {}", 
-            field_name, e, json_str
+            "Failed to parse {} string. Error: {}. Near: {}",
+            field_name, e, error_snippet(json_str, e.line(), e.column())
         ))
     })
-}
\ No newline at end of file
+}
+
+/// Up to 40 characters either side of `line`:`column` (1-based, as reported
+/// by serde_json), with `>>>` marking the error position.
+fn error_snippet(text: &str, line: usize, column: usize) -> String {
+    let line_start: usize = text.split_inclusive('\n').take(line.saturating_sub(1)).map(str::len).sum();
+    let mut pos = (line_start + column.saturating_sub(1)).min(text.len());
+    while !text.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    let before: Vec<char> = text[..pos].chars().rev().take(40).collect();
+    let after: String = text[pos..].chars().take(40).collect();
+    format!("...{}>>>{after}...", before.into_iter().rev().collect::<String>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_json_string_keeps_braces_inside_strings() {
+        let raw = r#"Here is the schema: {"type": "object", "description": "Totals {per project}", "properties": {"a": {"description": "ends with }"}}} Let me know!"#;
+        let schema = parse_json_string(raw, "output_schema").unwrap();
+        assert_eq!(schema["description"], "Totals {per project}");
+        assert_eq!(schema["properties"]["a"]["description"], "ends with }");
+    }
+
+    #[test]
+    fn parse_json_string_accepts_array_roots() {
+        let raw = "```json\n[{\"name\": \"a\"}, {\"name\": \"b\"}]\n```";
+        assert_eq!(parse_json_string(raw, "input_schema").unwrap(), json!([{ "name": "a" }, { "name": "b" }]));
+    }
+
+    #[test]
+    fn parse_json_string_reports_a_snippet_on_failure() {
+        let err = parse_json_string(r#"{"type": "object", "properties": {"a": nope}}"#, "input_schema").unwrap_err().to_string();
+        assert!(err.contains("input_schema"), "{err}");
+        assert!(err.contains(">>>"), "{err}");
+    }
+
+    #[test]
+    fn build_cost_estimate_grows_with_the_request() {
+        let swarm = AgentSwarm::with_client(Box::new(crate::ai::mock::MockClient::new()));
+        let short = swarm.estimate_build_cost("profit tool");
+        let long = swarm.estimate_build_cost(&"profit tool with per-project margins and overhead ".repeat(20));
+
+        let stages: Vec<&str> = short.stages.iter().map(|(stage, _)| stage.as_str()).collect();
+        assert_eq!(stages, ["Architecture", "Development", "QA"]);
+        assert!(short.stages[0].1 > estimate_tokens(&architect_system_prompt()));
+        assert_eq!(short.prompt_tokens, short.stages.iter().map(|(_, tokens)| tokens).sum::<usize>());
+        assert!(long.prompt_tokens > short.prompt_tokens);
+        for ((_, short), (_, long)) in short.stages.iter().zip(&long.stages) {
+            assert!(long > short);
+        }
+    }
+}
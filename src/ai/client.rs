@@ -1,51 +1,303 @@
 use crate::error::MetaError;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
 use serde_json::{json, Value};
+use tokio::sync::Semaphore;
 use tokio::time::{sleep, Duration};
+use std::collections::HashMap;
 use std::fs;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::hash::{BuildHasher, DefaultHasher, Hash, Hasher, RandomState};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 const GEMINI_MODEL: &str = "gemini-2.5-flash-preview-09-2025";
+const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+const GEMINI_API_VERSION: &str = "v1beta";
+
+/// A text-generation backend used by the agent swarm.
+///
+/// Implemented by `GeminiClient` for real calls; alternative providers or
+/// offline stand-ins only need to return the raw (JSON) response text.
+#[async_trait]
+pub trait LlmClient: Send + Sync {
+    async fn generate(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        response_schema: Option<Value>,
+        stage_name: &str,
+    ) -> Result<String, MetaError>;
+}
+
+#[async_trait]
+impl<T: LlmClient + ?Sized> LlmClient for Arc<T> {
+    async fn generate(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        response_schema: Option<Value>,
+        stage_name: &str,
+    ) -> Result<String, MetaError> {
+        (**self).generate(system_prompt, user_prompt, response_schema, stage_name).await
+    }
+}
+
+impl dyn LlmClient + '_ {
+    /// `generate`, then extract the JSON payload and deserialize it as `T`.
+    /// A parse failure is a `ValidationFailed` quoting the offending text.
+    pub async fn generate_typed<T: DeserializeOwned>(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        response_schema: Option<Value>,
+        stage_name: &str,
+    ) -> Result<T, MetaError> {
+        let text = self.generate(system_prompt, user_prompt, response_schema, stage_name).await?;
+        let json = extract_json(&text);
+        serde_json::from_str(json).map_err(|e| {
+            MetaError::ValidationFailed(format!("{stage_name} response parse failed: {e}. Response was: {json}"))
+        })
+    }
+}
+
+/// Token counts from the `usageMetadata` block of a Gemini response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub response_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl TokenUsage {
+    /// `None` when the response carries no `usageMetadata`.
+    pub fn from_body(body: &Value) -> Option<Self> {
+        let usage = body.get("usageMetadata")?;
+        let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+        Some(Self {
+            prompt_tokens: count("promptTokenCount"),
+            response_tokens: count("candidatesTokenCount"),
+            total_tokens: count("totalTokenCount"),
+        })
+    }
+}
+
+/// Hooks around each HTTP attempt made by `GeminiClient`, for metrics,
+/// billing or payload capture. Both methods default to no-ops.
+pub trait ClientObserver: Send + Sync {
+    /// Called with the JSON payload just before it is sent.
+    fn on_request(&self, _stage: &str, _payload: &Value) {}
+    /// Called with the decoded body of a successful response and the time
+    /// since the request was sent. Failed attempts are not reported here.
+    fn on_response(&self, _stage: &str, _body: &Value, _latency: Duration, _usage: Option<TokenUsage>) {}
+}
+
+/// Observer that ignores every call.
+pub struct NoopObserver;
+
+impl ClientObserver for NoopObserver {}
+
+/// Model selection and generation parameters for `GeminiClient`.
+#[derive(Debug, Clone)]
+pub struct GeminiConfig {
+    pub model: String,
+    pub temperature: Option<f32>,
+    pub max_output_tokens: Option<u32>,
+    pub timeout: Duration,
+    /// Attempts per `generate` call before giving up.
+    pub max_retries: u32,
+    /// Upper bound on the (jittered) delay between attempts.
+    pub max_backoff: Duration,
+    /// Scheme and host (plus any path prefix) of the API, e.g. a proxy or a
+    /// local mock server. Defaults to `GEMINI_BASE_URL` if set.
+    pub base_url: String,
+    /// Path segment before `models/`. Defaults to `GEMINI_API_VERSION` if set.
+    pub api_version: String,
+}
+
+impl Default for GeminiConfig {
+    fn default() -> Self {
+        Self {
+            model: GEMINI_MODEL.to_string(),
+            temperature: None,
+            max_output_tokens: None,
+            timeout: Duration::from_secs(60),
+            max_retries: 3,
+            max_backoff: Duration::from_secs(30),
+            base_url: env_or("GEMINI_BASE_URL", GEMINI_BASE_URL),
+            api_version: env_or("GEMINI_API_VERSION", GEMINI_API_VERSION),
+        }
+    }
+}
+
+/// A successful `generate_with_stats` call.
+#[derive(Debug, Clone)]
+pub struct GenerationOutcome {
+    pub text: String,
+    /// HTTP attempts made, including the successful one.
+    pub attempts: usize,
+    /// Total backoff slept between failed attempts.
+    pub total_wait: Duration,
+}
 
 pub struct GeminiClient {
     client: reqwest::Client,
     api_key: String,
+    config: GeminiConfig,
+    /// Directory that raw responses are written to. `None` disables dumping.
+    dump_dir: Option<PathBuf>,
+    /// Successful responses keyed by `cache_key`. `None` disables caching.
+    cache: Option<Arc<Mutex<HashMap<u64, String>>>>,
+    /// Scrub prompt text echoed back in error bodies and dumped responses.
+    redact_prompts: bool,
+    observer: Arc<dyn ClientObserver>,
+    /// Caps in-flight HTTP requests across everyone sharing this client.
+    limiter: Option<Arc<Semaphore>>,
 }
 
 impl GeminiClient {
-    pub fn new() -> Self {
-        Self {
+    pub fn new() -> Result<Self, MetaError> {
+        Self::with_config(GeminiConfig::default())
+    }
+
+    pub fn with_config(config: GeminiConfig) -> Result<Self, MetaError> {
+        Self::with_config_from(config, |key| std::env::var(key).ok())
+    }
+
+    /// `with_config`, reading `GEMINI_API_KEY` through `lookup`.
+    fn with_config_from(config: GeminiConfig, lookup: impl Fn(&str) -> Option<String>) -> Result<Self, MetaError> {
+        let api_key = lookup("GEMINI_API_KEY")
+            .filter(|k| !k.trim().is_empty())
+            .ok_or_else(|| MetaError::ConfigError("GEMINI_API_KEY must be set".into()))?;
+
+        Ok(Self {
             client: reqwest::Client::builder()
-                .timeout(Duration::from_secs(60))
+                .timeout(config.timeout)
                 .build()
-                .unwrap_or_default(),
-            api_key: std::env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY must be set"),
-        }
+                .map_err(|e| MetaError::ConfigError(format!("HTTP client: {e}")))?,
+            api_key,
+            config,
+            dump_dir: None,
+            cache: None,
+            redact_prompts: false,
+            observer: Arc::new(NoopObserver),
+            limiter: None,
+        })
+    }
+
+    /// Send requests to `base_url` instead of the public Gemini endpoint.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.config.base_url = base_url.into();
+        self
+    }
+
+    /// Write every cleaned response to `dir` as `llm_response_<stage>_<timestamp>.json`.
+    pub fn with_response_dump(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dump_dir = Some(dir.into());
+        self
+    }
+
+    /// Replace any verbatim system/user prompt text in logged API errors and
+    /// dumped responses with `[REDACTED PROMPT]`. The API key is never logged
+    /// or dumped regardless of this setting.
+    pub fn with_prompt_redaction(mut self) -> Self {
+        self.redact_prompts = true;
+        self
+    }
+
+    /// Report every request payload and successful response (with latency
+    /// and token usage) to `observer`.
+    pub fn with_observer(mut self, observer: Arc<dyn ClientObserver>) -> Self {
+        self.observer = observer;
+        self
     }
 
-    pub async fn generate(
+    /// Allow at most `n` requests in flight at once; further calls wait for a
+    /// slot. Backoff sleeps between retries do not hold a slot.
+    pub fn with_concurrency_limit(mut self, n: usize) -> Self {
+        self.limiter = Some(Arc::new(Semaphore::new(n.max(1))));
+        self
+    }
+
+    /// Typed `generate`; see `generate_typed` on `dyn LlmClient`.
+    pub async fn generate_typed<T: DeserializeOwned>(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        response_schema: Option<Value>,
+        stage_name: &str,
+    ) -> Result<T, MetaError> {
+        (self as &dyn LlmClient).generate_typed(system_prompt, user_prompt, response_schema, stage_name).await
+    }
+
+    /// Reuse responses for identical (system, user, schema, stage) requests for
+    /// the lifetime of this client.
+    pub fn with_cache(mut self) -> Self {
+        self.cache = Some(Arc::new(Mutex::new(HashMap::new())));
+        self
+    }
+}
+
+#[async_trait]
+impl LlmClient for GeminiClient {
+    async fn generate(
         &self,
         system_prompt: &str,
         user_prompt: &str,
         response_schema: Option<Value>,
         stage_name: &str,
     ) -> Result<String, MetaError> {
-        let max_retries = 3;
-        
+        self.generate_with_stats(system_prompt, user_prompt, response_schema, stage_name)
+            .await
+            .map(|outcome| outcome.text)
+    }
+}
+
+impl GeminiClient {
+    /// `generate`, also reporting how many HTTP attempts the call took and
+    /// how long it slept between them. A cache hit reports zero attempts.
+    pub async fn generate_with_stats(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        response_schema: Option<Value>,
+        stage_name: &str,
+    ) -> Result<GenerationOutcome, MetaError> {
+        let key = cache_key(system_prompt, user_prompt, response_schema.as_ref(), stage_name);
+        if let Some(cache) = &self.cache
+            && let Some(hit) = cache.lock().unwrap().get(&key)
+        {
+            log::info!("♻️  Using cached response for stage '{stage_name}'");
+            return Ok(GenerationOutcome { text: hit.clone(), attempts: 0, total_wait: Duration::ZERO });
+        }
+
+        let max_retries = self.config.max_retries.max(1);
+        let mut total_wait = Duration::ZERO;
+
         for attempt in 1..=max_retries {
             match self.generate_attempt(system_prompt, user_prompt, response_schema.clone(), stage_name).await {
-                Ok(text) => return Ok(text),
+                Ok(text) => {
+                    if let Some(cache) = &self.cache {
+                        cache.lock().unwrap().insert(key, text.clone());
+                    }
+                    return Ok(GenerationOutcome { text, attempts: attempt as usize, total_wait });
+                },
                 Err(e) => {
                     log::warn!("Attempt {attempt}/{max_retries} failed: {e}");
-                    if attempt == max_retries {
+                    if attempt == max_retries || !e.is_retryable() {
                         return Err(e);
                     }
-                    sleep(Duration::from_secs(2u64.pow(attempt as u32))).await;
+                    let delay = backoff_delay(attempt, self.config.max_backoff, random_unit());
+                    total_wait += delay;
+                    sleep(delay).await;
                 }
             }
         }
         Err(MetaError::GenerationFailed("Max retries exceeded".into()))
     }
+}
 
+impl GeminiClient {
     async fn generate_attempt(
         &self,
         system_prompt: &str,
@@ -53,11 +305,75 @@ impl GeminiClient {
         response_schema: Option<Value>,
         stage_name: &str,
     ) -> Result<String, MetaError> {
+        // The key travels in a header so it never appears in URLs that
+        // reqwest errors (and therefore logs) may include.
         let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            GEMINI_MODEL, self.api_key
+            "{}/{}/models/{}:generateContent",
+            self.config.base_url.trim_end_matches('/'),
+            self.config.api_version.trim_matches('/'),
+            self.config.model
         );
 
+        let payload = self.build_payload(system_prompt, user_prompt, response_schema);
+
+        let _permit = match &self.limiter {
+            Some(limiter) => Some(limiter.acquire().await.map_err(|_| {
+                MetaError::GenerationFailed("Concurrency limiter closed".into())
+            })?),
+            None => None,
+        };
+        self.observer.on_request(stage_name, &payload);
+        let sent = Instant::now();
+        let res = self.client.post(&url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&payload)
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            let status = res.status();
+            let err_text = self.redact(&res.text().await.unwrap_or_default(), &[system_prompt, user_prompt]);
+            log::error!("API Error: {}", err_text);
+            return Err(MetaError::HttpStatus {
+                status: status.as_u16(),
+                message: err_text,
+                retryable: is_retryable_status(status.as_u16()),
+            });
+        }
+
+        let body: Value = res.json().await?;
+        let usage = TokenUsage::from_body(&body);
+        if let Some(usage) = usage {
+            log::debug!("Stage '{stage_name}' used {} tokens", usage.total_tokens);
+        }
+        self.observer.on_response(stage_name, &body, sent.elapsed(), usage);
+
+        let text = body["candidates"][0]["content"]["parts"][0]["text"]
+            .as_str()
+            .ok_or_else(|| MetaError::GenerationFailed("No text content returned".into()))?;
+
+        let cleaned_text = extract_json(text).to_string();
+
+        if let Some(dir) = &self.dump_dir {
+            dump_response(dir, stage_name, &self.redact(&cleaned_text, &[system_prompt, user_prompt]));
+        }
+
+        Ok(cleaned_text)
+    }
+
+    /// Remove the API key (always) and, with `with_prompt_redaction`, any
+    /// verbatim copy of `prompts` from text bound for logs or dump files.
+    fn redact(&self, text: &str, prompts: &[&str]) -> String {
+        let mut redacted = text.replace(&self.api_key, "[REDACTED KEY]");
+        if self.redact_prompts {
+            for prompt in prompts.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+                redacted = redacted.replace(prompt, "[REDACTED PROMPT]");
+            }
+        }
+        redacted
+    }
+
+    fn build_payload(&self, system_prompt: &str, user_prompt: &str, response_schema: Option<Value>) -> Value {
         let full_prompt = format!("{system_prompt}\n\n{user_prompt}");
 
         let mut payload = json!({
@@ -72,47 +388,478 @@ impl GeminiClient {
         if let Some(schema) = response_schema {
             payload["generationConfig"]["responseSchema"] = schema;
         }
+        if let Some(temperature) = self.config.temperature {
+            payload["generationConfig"]["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = self.config.max_output_tokens {
+            payload["generationConfig"]["maxOutputTokens"] = json!(max_tokens);
+        }
 
-        let res = self.client.post(&url).json(&payload).send().await?;
+        payload
+    }
+}
 
-        if !res.status().is_success() {
-            let status = res.status();
-            let err_text = res.text().await.unwrap_or_default();
-            log::error!("API Error: {}", err_text);
-            return Err(MetaError::GenerationFailed(format!("API Error {status}: {err_text}")));
-        }
+fn env_or(key: &str, default: &str) -> String {
+    env_or_with(|key| std::env::var(key).ok(), key, default)
+}
 
-        let body: Value = res.json().await?;
-        
-        let text = body["candidates"][0]["content"]["parts"][0]["text"]
-            .as_str()
-            .ok_or_else(|| MetaError::GenerationFailed("No text content returned".into()))?;
+/// The non-blank value `lookup` finds for `key`, else `default`.
+fn env_or_with(lookup: impl Fn(&str) -> Option<String>, key: &str, default: &str) -> String {
+    lookup(key)
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
 
-        let cleaned_text = clean_json_block(text);
+/// Delay before retrying after failed `attempt` (1-based): `2^attempt` seconds
+/// scaled by a jitter factor in `[0.5, 1.5)` derived from `unit` (in `[0, 1)`),
+/// capped at `max_backoff`. Jitter keeps concurrent callers from retrying in
+/// lockstep; passing a fixed `unit` makes the delay deterministic.
+pub fn backoff_delay(attempt: u32, max_backoff: Duration, unit: f64) -> Duration {
+    let base = 2f64.powi(attempt.min(30) as i32);
+    let jittered = Duration::from_secs_f64(base * (0.5 + unit.clamp(0.0, 1.0)));
+    jittered.min(max_backoff)
+}
+
+/// Uniform-ish value in `[0, 1)` from the std hasher's random keys.
+fn random_unit() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Timeouts (408), rate limits (429) and server errors (5xx) are transient;
+/// other statuses such as 400/401/403 will fail the same way again.
+fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500..=599)
+}
+
+fn cache_key(system_prompt: &str, user_prompt: &str, response_schema: Option<&Value>, stage_name: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    system_prompt.hash(&mut hasher);
+    user_prompt.hash(&mut hasher);
+    response_schema.map(|s| s.to_string()).hash(&mut hasher);
+    stage_name.hash(&mut hasher);
+    hasher.finish()
+}
 
-        // --- DUMP RESPONSE TO TIMESTAMPED FILE ---
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        
-        // Sanitize stage name
-        let safe_stage = stage_name.replace(" ", "_").replace("/", "-");
-        let filename = format!("llm_response_{}_{}.json", safe_stage, timestamp);
+fn dump_response(dir: &Path, stage_name: &str, text: &str) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
 
-        if let Err(e) = fs::write(&filename, &cleaned_text) {
-            log::warn!("Failed to dump response to {}: {}", filename, e);
-        } else {
-            log::info!("💾 LLM Response dumped to '{}'", filename);
+    // Sanitize stage name
+    let safe_stage = stage_name.replace(" ", "_").replace("/", "-");
+    let filename = dir.join(format!("llm_response_{}_{}.json", safe_stage, timestamp));
+
+    if let Err(e) = fs::create_dir_all(dir) {
+        log::warn!("Failed to create dump directory {}: {}", dir.display(), e);
+        return;
+    }
+
+    if let Err(e) = fs::write(&filename, text) {
+        log::warn!("Failed to dump response to {}: {}", filename.display(), e);
+    } else {
+        log::info!("💾 LLM Response dumped to '{}'", filename.display());
+    }
+}
+
+/// Rough token count for `text` (about four characters per token). Good
+/// enough for budgeting; the API's `usageMetadata` is authoritative.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Locate the JSON payload in a model response: the body of the first
+/// ```` ``` ```` fence if there is one, then the longest bracket-balanced
+/// object or array that parses, skipping any prose around it (brackets inside
+/// string literals don't count). Returns the trimmed text unchanged when no
+/// such span is found.
+pub fn extract_json(text: &str) -> &str {
+    let mut body = text.trim();
+    if let Some(open) = body.find("```") {
+        let after = &body[open + 3..];
+        // Skip the info string (e.g. `json`) on the fence line.
+        let content = after.find('\n').map_or(after, |nl| &after[nl + 1..]);
+        body = content.find("```").map_or(content, |close| &content[..close]).trim();
+    }
+
+    // Keep the longest valid top-level span, so a stray `[1]` in prose
+    // doesn't win over the payload.
+    let mut best: Option<&str> = None;
+    let mut start = 0;
+    while let Some(offset) = body[start..].find(['{', '[']) {
+        let open = start + offset;
+        let span = balanced_len(&body[open..])
+            .map(|len| &body[open..open + len])
+            .filter(|span| serde_json::from_str::<serde::de::IgnoredAny>(span).is_ok());
+        match span {
+            Some(span) => {
+                if best.is_none_or(|b| span.len() > b.len()) {
+                    best = Some(span);
+                }
+                start = open + span.len();
+            }
+            None => start = open + 1,
         }
-        // -----------------------------------------
+    }
+    best.unwrap_or(body)
+}
 
-        Ok(cleaned_text)
+/// Byte length of the bracketed value opening `text`, or `None` if it never
+/// closes or closes with the wrong bracket.
+fn balanced_len(text: &str) -> Option<usize> {
+    let mut open = Vec::new();
+    let (mut in_string, mut escaped) = (false, false);
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => open.push('}'),
+            '[' => open.push(']'),
+            '}' | ']' => {
+                if open.pop() != Some(c) {
+                    return None;
+                }
+                if open.is_empty() {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
     }
+    None
 }
 
-fn clean_json_block(text: &str) -> String {
-    let start = text.find("```json").map(|i| i + 7).unwrap_or(0);
-    let end = text.rfind("```").unwrap_or(text.len());
-    text[start..end].trim().to_string()
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    const TEST_KEY: &str = "test-key-0123456789";
+
+    /// Client for a local `serve` URL, with the test key and no backoff.
+    fn test_client(base_url: &str, config: GeminiConfig) -> GeminiClient {
+        let config = GeminiConfig { base_url: base_url.to_string(), max_backoff: Duration::ZERO, ..config };
+        GeminiClient::with_config_from(config, |_| Some(TEST_KEY.to_string())).unwrap()
+    }
+
+    /// A Gemini response body whose only candidate text is `text`.
+    fn gemini_body(text: &str) -> String {
+        json!({ "candidates": [{ "content": { "parts": [{ "text": text }] } }] }).to_string()
+    }
+
+    /// HTTP server on a random local port answering each request, in order,
+    /// with the next `(status, body)` (the last one repeats). Returns the base
+    /// URL and the raw requests received.
+    fn serve(responses: Vec<(u16, String)>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        std::thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let Ok(mut stream) = stream else { break };
+                seen.lock().unwrap().push(read_request(&mut stream));
+                let (status, body) = &responses[i.min(responses.len() - 1)];
+                let reply = format!(
+                    "HTTP/1.1 {status} Test\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(reply.as_bytes());
+            }
+        });
+        (format!("http://{addr}"), requests)
+    }
+
+    fn read_request(stream: &mut TcpStream) -> String {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = stream.read(&mut chunk).unwrap_or(0);
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            let text = String::from_utf8_lossy(&buf);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let body_len = text[..end].lines()
+                    .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap_or(0)))
+                    .unwrap_or(0);
+                if buf.len() >= end + 4 + body_len {
+                    break;
+                }
+            }
+        }
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+
+    /// `llm_response_*` files directly inside `dir`.
+    fn dump_files(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir).map(|entries| {
+            entries.flatten()
+                .map(|e| e.path())
+                .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with("llm_response_")))
+                .collect()
+        }).unwrap_or_default()
+    }
+
+    #[test]
+    fn client_errors_without_api_key() {
+        for value in [None, Some("   ")] {
+            let lookup = |key: &str| value.filter(|_| key == "GEMINI_API_KEY").map(str::to_string);
+            let err = GeminiClient::with_config_from(GeminiConfig::default(), lookup).err().expect("missing key must be an error");
+            assert!(matches!(&err, MetaError::ConfigError(msg) if msg.contains("GEMINI_API_KEY")), "{err}");
+        }
+    }
+
+    #[tokio::test]
+    async fn responses_are_not_dumped_by_default() {
+        let (url, _) = serve(vec![(200, gemini_body(r#"{"ok": true}"#))]);
+        let before = dump_files(Path::new(".")).len();
+        let client = test_client(&url, GeminiConfig::default());
+        client.generate("sys", "user", None, "NoDump").await.unwrap();
+        assert!(client.dump_dir.is_none());
+        assert_eq!(dump_files(Path::new(".")).len(), before);
+    }
+
+    #[tokio::test]
+    async fn dump_directory_is_created_and_written() {
+        let (url, _) = serve(vec![(200, gemini_body(r#"{"ok": true}"#))]);
+        let root = std::env::temp_dir().join(format!("meta-ai-dump-{}", uuid::Uuid::new_v4()));
+        let dir = root.join("nested");
+        let client = test_client(&url, GeminiConfig::default()).with_response_dump(&dir);
+        client.generate("sys", "user", None, "Dump Stage").await.unwrap();
+
+        let files = dump_files(&dir);
+        assert_eq!(files.len(), 1);
+        assert!(files[0].to_string_lossy().contains("llm_response_Dump_Stage_"));
+        assert_eq!(fs::read_to_string(&files[0]).unwrap(), r#"{"ok": true}"#);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn payload_carries_configured_model_and_parameters() {
+        let (url, requests) = serve(vec![(200, gemini_body("{}"))]);
+        let config = GeminiConfig {
+            model: "gemini-pinned-001".into(),
+            temperature: Some(0.25),
+            max_output_tokens: Some(512),
+            ..GeminiConfig::default()
+        };
+        let client = test_client(&url, config);
+        client.generate("sys", "user", Some(json!({ "type": "object" })), "Params").await.unwrap();
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert!(request.starts_with("POST /v1beta/models/gemini-pinned-001:generateContent "), "{request}");
+        let body: Value = serde_json::from_str(&request[request.find("\r\n\r\n").unwrap() + 4..]).unwrap();
+        assert_eq!(body["generationConfig"]["temperature"], json!(0.25));
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], json!(512));
+        assert_eq!(body["generationConfig"]["responseSchema"], json!({ "type": "object" }));
+    }
+
+    #[test]
+    fn default_payload_omits_generation_parameters() {
+        let client = test_client("http://unused", GeminiConfig::default());
+        let payload = client.build_payload("sys", "user", None);
+        assert_eq!(payload["generationConfig"], json!({ "responseMimeType": "application/json" }));
+        assert_eq!(client.config.model, GEMINI_MODEL);
+    }
+
+    #[tokio::test]
+    async fn cache_serves_repeated_requests_without_http() {
+        let (url, requests) = serve(vec![(200, gemini_body("[1]"))]);
+        let client = test_client(&url, GeminiConfig::default()).with_cache();
+        let first = client.generate_with_stats("sys", "user", None, "QA").await.unwrap();
+        let second = client.generate_with_stats("sys", "user", None, "QA").await.unwrap();
+        assert_eq!((first.attempts, second.attempts), (1, 0));
+        assert_eq!(second.text, "[1]");
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        client.generate("sys", "other user", None, "QA").await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn cache_is_off_by_default() {
+        let (url, requests) = serve(vec![(200, gemini_body("[1]"))]);
+        let client = test_client(&url, GeminiConfig::default());
+        client.generate("sys", "user", None, "QA").await.unwrap();
+        client.generate("sys", "user", None, "QA").await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn client_errors_fail_without_retrying() {
+        let (url, requests) = serve(vec![(400, r#"{"error": "invalid schema"}"#.into())]);
+        let client = test_client(&url, GeminiConfig { max_retries: 3, ..GeminiConfig::default() });
+        let err = client.generate("sys", "user", None, "BadRequest").await.unwrap_err();
+        assert!(matches!(err, MetaError::HttpStatus { status: 400, retryable: false, .. }), "{err}");
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unavailable_responses_are_retried() {
+        let (url, requests) = serve(vec![(503, "overloaded".into())]);
+        let client = test_client(&url, GeminiConfig { max_retries: 3, ..GeminiConfig::default() });
+        let err = client.generate("sys", "user", None, "Unavailable").await.unwrap_err();
+        assert!(matches!(err, MetaError::HttpStatus { status: 503, retryable: true, .. }), "{err}");
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn backoff_delay_is_jittered_and_capped() {
+        let cap = Duration::from_secs(30);
+        assert_eq!(backoff_delay(1, cap, 0.0), Duration::from_secs(1));
+        assert_eq!(backoff_delay(1, cap, 0.5), Duration::from_secs(2));
+        assert_eq!(backoff_delay(3, cap, 0.25), Duration::from_secs(6));
+        assert_eq!(backoff_delay(3, Duration::from_secs(5), 0.9), Duration::from_secs(5));
+        assert_eq!(backoff_delay(40, cap, 0.0), cap);
+        for _ in 0..100 {
+            let delay = backoff_delay(2, cap, random_unit());
+            assert!((Duration::from_secs(2)..Duration::from_secs(6)).contains(&delay), "{delay:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn api_key_never_reaches_urls_errors_or_dumps() {
+        let echo = format!("bad key {TEST_KEY} for prompt 'secret plan'");
+        let (url, requests) = serve(vec![(400, echo.clone())]);
+        let err = test_client(&url, GeminiConfig::default())
+            .generate("sys", "secret plan", None, "Leak").await.unwrap_err();
+        assert!(!err.to_string().contains(TEST_KEY), "{err}");
+        assert!(err.to_string().contains("[REDACTED KEY]"));
+        let request_line = requests.lock().unwrap()[0].lines().next().unwrap().to_string();
+        assert!(!request_line.contains(TEST_KEY), "{request_line}");
+
+        let (url, _) = serve(vec![(200, gemini_body(&json!({ "echo": echo }).to_string()))]);
+        let root = std::env::temp_dir().join(format!("meta-ai-redact-{}", uuid::Uuid::new_v4()));
+        let client = test_client(&url, GeminiConfig::default()).with_response_dump(&root).with_prompt_redaction();
+        client.generate("sys", "secret plan", None, "Leak").await.unwrap();
+        let dumped = fs::read_to_string(&dump_files(&root)[0]).unwrap();
+        assert!(!dumped.contains(TEST_KEY) && !dumped.contains("secret plan"), "{dumped}");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn extract_json_handles_fences_and_prose() {
+        let fenced_array = "```json\n[{\"id\": \"a\"}, {\"id\": \"b\"}]\n```";
+        assert_eq!(extract_json(fenced_array), r#"[{"id": "a"}, {"id": "b"}]"#);
+
+        let fenced_object = "Here you go:\n```\n{\"name\": \"App\"}\n```\nLet me know!";
+        assert_eq!(extract_json(fenced_object), r#"{"name": "App"}"#);
+
+        let prose = r#"Sure [1] - the definition is {"name": "App", "note": "uses } and ]"} as requested."#;
+        assert_eq!(extract_json(prose), r#"{"name": "App", "note": "uses } and ]"}"#);
+
+        assert_eq!(extract_json("  no json here  "), "no json here");
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        requests: Mutex<Vec<String>>,
+        responses: Mutex<Vec<(String, Duration, Option<TokenUsage>)>>,
+    }
+
+    impl ClientObserver for Recorder {
+        fn on_request(&self, stage: &str, _payload: &Value) {
+            self.requests.lock().unwrap().push(stage.to_string());
+        }
+        fn on_response(&self, stage: &str, _body: &Value, latency: Duration, usage: Option<TokenUsage>) {
+            self.responses.lock().unwrap().push((stage.to_string(), latency, usage));
+        }
+    }
+
+    #[tokio::test]
+    async fn observer_sees_stage_latency_and_usage() {
+        let mut body: Value = serde_json::from_str(&gemini_body("[]")).unwrap();
+        body["usageMetadata"] = json!({ "promptTokenCount": 12, "candidatesTokenCount": 3, "totalTokenCount": 15 });
+        let (url, _) = serve(vec![(503, "busy".into()), (200, body.to_string())]);
+        let recorder = Arc::new(Recorder::default());
+        let client = test_client(&url, GeminiConfig::default()).with_observer(recorder.clone());
+        client.generate("sys", "user", None, "QA").await.unwrap();
+
+        assert_eq!(*recorder.requests.lock().unwrap(), ["QA", "QA"]);
+        let responses = recorder.responses.lock().unwrap();
+        let [(stage, latency, usage)] = &responses[..] else { panic!("failed attempts must not be reported") };
+        assert_eq!(stage, "QA");
+        assert!(*latency > Duration::ZERO);
+        assert_eq!(*usage, Some(TokenUsage { prompt_tokens: 12, response_tokens: 3, total_tokens: 15 }));
+    }
+
+    #[tokio::test]
+    async fn requests_go_to_the_configured_base_url() {
+        let (url, requests) = serve(vec![(200, gemini_body("{}"))]);
+        let config = GeminiConfig { api_version: "v1".into(), ..GeminiConfig::default() };
+        let client = test_client("http://unused", config).with_base_url(format!("{url}/"));
+        client.generate("sys", "user", None, "Proxy").await.unwrap();
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert!(request.starts_with(&format!("POST /v1/models/{GEMINI_MODEL}:generateContent ")), "{request}");
+    }
+
+    #[test]
+    fn base_url_defaults_from_the_environment() {
+        let env = |key: &str| (key == "GEMINI_BASE_URL").then(|| "http://proxy.local:8080".to_string());
+        assert_eq!(env_or_with(env, "GEMINI_BASE_URL", GEMINI_BASE_URL), "http://proxy.local:8080");
+        assert_eq!(env_or_with(env, "GEMINI_API_VERSION", GEMINI_API_VERSION), GEMINI_API_VERSION);
+        assert_eq!(env_or_with(|_| Some("  ".into()), "GEMINI_BASE_URL", GEMINI_BASE_URL), GEMINI_BASE_URL);
+    }
+
+    #[tokio::test]
+    async fn stats_count_the_failed_attempt() {
+        let (url, requests) = serve(vec![(503, "busy".into()), (200, gemini_body("[1]"))]);
+        let client = test_client(&url, GeminiConfig::default());
+        let outcome = client.generate_with_stats("sys", "user", None, "QA").await.unwrap();
+        assert_eq!((outcome.text.as_str(), outcome.attempts), ("[1]", 2));
+        // `test_client` caps the backoff at zero.
+        assert_eq!(outcome.total_wait, Duration::ZERO);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    /// Tracks requests between `on_request` and `on_response`.
+    #[derive(Default)]
+    struct InFlight {
+        current: Mutex<usize>,
+        peak: Mutex<usize>,
+    }
+
+    impl ClientObserver for InFlight {
+        fn on_request(&self, _stage: &str, _payload: &Value) {
+            let mut current = self.current.lock().unwrap();
+            *current += 1;
+            let mut peak = self.peak.lock().unwrap();
+            *peak = (*peak).max(*current);
+        }
+        fn on_response(&self, _stage: &str, _body: &Value, _latency: Duration, _usage: Option<TokenUsage>) {
+            *self.current.lock().unwrap() -= 1;
+        }
+    }
+
+    async fn peak_in_flight(limit: Option<usize>) -> usize {
+        let (url, _) = serve(vec![(200, gemini_body("[]"))]);
+        let observer = Arc::new(InFlight::default());
+        let mut client = test_client(&url, GeminiConfig::default()).with_observer(observer.clone());
+        if let Some(limit) = limit {
+            client = client.with_concurrency_limit(limit);
+        }
+        let (a, b) = tokio::join!(client.generate("sys", "a", None, "QA"), client.generate("sys", "b", None, "QA"));
+        a.unwrap();
+        b.unwrap();
+        *observer.peak.lock().unwrap()
+    }
+
+    #[tokio::test]
+    async fn concurrency_limit_serializes_requests() {
+        assert_eq!(peak_in_flight(None).await, 2);
+        assert_eq!(peak_in_flight(Some(1)).await, 1);
+    }
+}
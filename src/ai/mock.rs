@@ -0,0 +1,173 @@
+use super::client::LlmClient;
+use crate::error::MetaError;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+#[cfg(any(test, feature = "mock"))]
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+#[cfg(any(test, feature = "mock"))]
+use std::sync::Mutex;
+
+/// A single `generate` call observed by `MockClient`.
+#[cfg(any(test, feature = "mock"))]
+#[derive(Debug, Clone)]
+pub struct MockCall {
+    pub stage: String,
+    pub system_prompt: String,
+    pub user_prompt: String,
+}
+
+/// Offline `LlmClient` that serves canned responses per stage name.
+///
+/// Responses queued for a stage are returned in order; the last one is
+/// repeated once the queue is down to a single entry. Every call is recorded
+/// so the orchestration loop can be inspected afterwards. Only built for
+/// tests or with the `mock` feature.
+#[cfg(any(test, feature = "mock"))]
+#[derive(Default)]
+pub struct MockClient {
+    responses: Mutex<HashMap<String, VecDeque<String>>>,
+    calls: Mutex<Vec<MockCall>>,
+}
+
+#[cfg(any(test, feature = "mock"))]
+impl MockClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_response(self, stage: &str, response: impl Into<String>) -> Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(stage.to_string())
+            .or_default()
+            .push_back(response.into());
+        self
+    }
+
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    pub fn call_count(&self, stage: &str) -> usize {
+        self.calls.lock().unwrap().iter().filter(|c| c.stage == stage).count()
+    }
+}
+
+#[cfg(any(test, feature = "mock"))]
+#[async_trait]
+impl LlmClient for MockClient {
+    async fn generate(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        _response_schema: Option<Value>,
+        stage_name: &str,
+    ) -> Result<String, MetaError> {
+        self.calls.lock().unwrap().push(MockCall {
+            stage: stage_name.to_string(),
+            system_prompt: system_prompt.to_string(),
+            user_prompt: user_prompt.to_string(),
+        });
+
+        let mut responses = self.responses.lock().unwrap();
+        let queue = responses.get_mut(stage_name).ok_or_else(|| {
+            MetaError::GenerationFailed(format!("No mock response for stage '{stage_name}'"))
+        })?;
+
+        let text = if queue.len() > 1 { queue.pop_front() } else { queue.front().cloned() };
+        text.ok_or_else(|| MetaError::GenerationFailed(format!("No mock response for stage '{stage_name}'")))
+    }
+}
+
+/// Offline `LlmClient` replaying pre-recorded responses from a fixture
+/// directory, one `<stage>.json` file per stage ("Architecture",
+/// "Development", "QA", "Fixer"). Lets `build_application` run end-to-end
+/// without an API key or network, e.g. for demos and CI.
+pub struct StaticClient {
+    responses: HashMap<String, String>,
+}
+
+impl StaticClient {
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, MetaError> {
+        let dir = dir.as_ref();
+        let entries = fs::read_dir(dir).map_err(|e| {
+            MetaError::ConfigError(format!("Cannot read fixture directory {}: {e}", dir.display()))
+        })?;
+
+        let mut responses = HashMap::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(stage) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            let text = fs::read_to_string(&path).map_err(|e| {
+                MetaError::ConfigError(format!("Cannot read fixture {}: {e}", path.display()))
+            })?;
+            responses.insert(stage.to_string(), text);
+        }
+        Ok(Self { responses })
+    }
+}
+
+#[async_trait]
+impl LlmClient for StaticClient {
+    async fn generate(
+        &self,
+        _system_prompt: &str,
+        _user_prompt: &str,
+        _response_schema: Option<Value>,
+        stage_name: &str,
+    ) -> Result<String, MetaError> {
+        self.responses.get(stage_name).cloned().ok_or_else(|| {
+            MetaError::GenerationFailed(format!("No recorded response for stage '{stage_name}'"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn mock_replays_queued_responses_and_repeats_the_last() {
+        let client = MockClient::new().with_response("QA", "[1]").with_response("QA", "[2]");
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            seen.push(client.generate("sys", "user", None, "QA").await.unwrap());
+        }
+        assert_eq!(seen, ["[1]", "[2]", "[2]"]);
+        assert_eq!(client.call_count("QA"), 3);
+        assert_eq!(client.calls()[0].user_prompt, "user");
+    }
+
+    #[tokio::test]
+    async fn mock_errors_for_unknown_stage() {
+        let err = MockClient::new().generate("sys", "user", None, "Fixer").await.unwrap_err();
+        assert!(err.to_string().contains("Fixer"), "{err}");
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Project {
+        name: String,
+        revenue: f64,
+    }
+
+    #[tokio::test]
+    async fn generate_typed_deserializes_the_payload() {
+        let mock = MockClient::new()
+            .with_response("QA", "Here:\n```json\n[{\"name\": \"A\", \"revenue\": 10}]\n```")
+            .with_response("QA", "[{\"name\": \"A\"}]");
+        let client: &dyn LlmClient = &mock;
+
+        let projects: Vec<Project> = client.generate_typed("sys", "user", None, "QA").await.unwrap();
+        assert_eq!(projects, [Project { name: "A".into(), revenue: 10.0 }]);
+
+        let err = client.generate_typed::<Vec<Project>>("sys", "user", None, "QA").await.unwrap_err();
+        assert!(matches!(&err, MetaError::ValidationFailed(msg) if msg.contains(r#"[{"name": "A"}]"#)), "{err}");
+    }
+}
@@ -1,5 +1,6 @@
 use serde_json::{json, Map, Value};
 use serde::Serialize;
+use std::fmt;
 
 pub fn clean_schema<T: Serialize>(root: T) -> serde_json::Result<Value> {
     let mut root_val = serde_json::to_value(root)?;
@@ -13,7 +14,7 @@ pub fn clean_schema<T: Serialize>(root: T) -> serde_json::Result<Value> {
         .cloned()
         .unwrap_or_default();
 
-    process_schema_node(&mut root_val, &definitions, 0);
+    process_schema_node(&mut root_val, &definitions, &mut Vec::new());
 
     if let Value::Object(ref mut map) = root_val {
         map.remove("$schema");
@@ -26,17 +27,166 @@ pub fn clean_schema<T: Serialize>(root: T) -> serde_json::Result<Value> {
     Ok(root_val)
 }
 
-fn process_schema_node(node: &mut Value, definitions: &Map<String, Value>, depth: usize) {
-    // 0. Recursion Guard
-    if depth > 20 {
-        *node = json!({ "type": "object", "nullable": true });
+/// Synthesize a minimal example value that satisfies `schema`: the first
+/// `enum`/`const` value when given, otherwise a per-type default (`""`, `0` or
+/// `minimum`, `false`), one sample element for arrays and every declared
+/// property for objects. `anyOf`/`oneOf` use their first non-null variant.
+pub fn sample_from_schema(schema: &Value) -> Value {
+    if let Some(value) = schema.get("const") {
+        return value.clone();
+    }
+    if let Some(first) = schema.get("enum").and_then(Value::as_array).and_then(|e| e.first()) {
+        return first.clone();
+    }
+    for key in ["anyOf", "oneOf", "allOf"] {
+        if let Some(variants) = schema.get(key).and_then(Value::as_array) {
+            let variant = variants.iter().find(|v| v.get("type") != Some(&json!("null")));
+            if let Some(variant) = variant {
+                return sample_from_schema(variant);
+            }
+        }
+    }
+
+    let schema_type = match schema.get("type") {
+        Some(Value::String(t)) => Some(t.as_str()),
+        Some(Value::Array(ts)) => ts.iter().filter_map(Value::as_str).find(|t| *t != "null"),
+        _ => None,
+    };
+    let schema_type = schema_type.or_else(|| {
+        if schema.get("properties").is_some() { Some("object") }
+        else if schema.get("items").is_some() { Some("array") }
+        else { None }
+    });
+
+    match schema_type {
+        Some("object") => {
+            let sample: Map<String, Value> = schema.get("properties")
+                .and_then(Value::as_object)
+                .map(|props| props.iter().map(|(k, v)| (k.clone(), sample_from_schema(v))).collect())
+                .unwrap_or_default();
+            Value::Object(sample)
+        }
+        Some("array") => match schema.get("items") {
+            Some(items) => json!([sample_from_schema(items)]),
+            None => json!([]),
+        },
+        Some("string") => json!(""),
+        Some("number") => schema.get("minimum").cloned().unwrap_or(json!(0.0)),
+        Some("integer") => schema.get("minimum").cloned().unwrap_or(json!(0)),
+        Some("boolean") => json!(false),
+        _ => Value::Null,
+    }
+}
+
+/// One failed check from `validate_value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// JSON pointer to the offending value (`""` for the root).
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{path}: {}", self.message)
+    }
+}
+
+/// Check `value` against the subset of JSON Schema this crate produces:
+/// `type`, `required`, `properties`, `items`, `enum` and `nullable`. Every
+/// violation is returned, not just the first.
+pub fn validate_value(value: &Value, schema: &Value) -> Result<(), Vec<SchemaViolation>> {
+    let mut violations = Vec::new();
+    check_node(value, schema, "", &mut violations);
+    if violations.is_empty() { Ok(()) } else { Err(violations) }
+}
+
+fn check_node(value: &Value, schema: &Value, path: &str, violations: &mut Vec<SchemaViolation>) {
+    let mut violation = |path: String, message: String| violations.push(SchemaViolation { path, message });
+
+    if value.is_null() && schema.get("nullable").and_then(|v| v.as_bool()).unwrap_or(false) {
         return;
     }
 
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|t| matches_type(value, t)) {
+            violation(path.to_string(), format!("expected {}, got {}", types.join(" or "), type_name(value)));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(|v| v.as_array())
+        && !allowed.iter().any(|a| a == value || a.as_f64().is_some_and(|n| value.as_f64() == Some(n)))
+    {
+        violation(path.to_string(), format!("expected one of {}, got {value}", Value::Array(allowed.clone())));
+    }
+
+    if let Some(obj) = value.as_object() {
+        if let Some(required) = schema.get("required").and_then(|v| v.as_array()) {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if !obj.contains_key(key) {
+                    violation(format!("{path}/{}", escape_pointer(key)), "missing required property".to_string());
+                }
+            }
+        }
+        if let Some(props) = schema.get("properties").and_then(|v| v.as_object()) {
+            for (key, prop_schema) in props {
+                if let Some(child) = obj.get(key) {
+                    check_node(child, prop_schema, &format!("{path}/{}", escape_pointer(key)), violations);
+                }
+            }
+        }
+    }
+
+    if let (Some(arr), Some(items)) = (value.as_array(), schema.get("items")) {
+        for (i, item) in arr.iter().enumerate() {
+            check_node(item, items, &format!("{path}/{i}"), violations);
+        }
+    }
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+pub(crate) fn matches_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+pub(crate) fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn process_schema_node(node: &mut Value, definitions: &Map<String, Value>, ref_path: &mut Vec<String>) {
     // 1. Resolve $ref loop
     // We do this BEFORE matching on Value::Object to avoid holding a borrow on 'map'
     // while trying to assign to '*node'.
-    let mut resolve_attempts = 0;
+    // `ref_path` holds the definitions being expanded above this node. Meeting one
+    // of them again means a genuine cycle, which is truncated at that point;
+    // deep but acyclic schemas are expanded in full.
+    let mut pushed = 0;
     loop {
         // Peek to see if we have a $ref
         let ref_target = if let Value::Object(map) = node {
@@ -45,23 +195,24 @@ fn process_schema_node(node: &mut Value, definitions: &Map<String, Value>, depth
             None
         };
 
-        if let Some(def_name_full) = ref_target {
-            resolve_attempts += 1;
-            if resolve_attempts > 10 { 
-                // Stop trying to resolve to prevent infinite loops
-                break; 
-            }
+        let Some(def_name_full) = ref_target else {
+            break; // No ref found, safe to proceed
+        };
 
-            let def_name = def_name_full.split('/').next_back().unwrap_or_default();
-            if let Some(def) = definitions.get(def_name) {
-                *node = def.clone();
-                // Loop continues to check if the new node is also a ref
-            } else {
-                *node = json!({ "type": "object", "description": "Unresolvable reference" });
-                break; 
-            }
+        let def_name = def_name_full.split('/').next_back().unwrap_or_default().to_string();
+        if ref_path.contains(&def_name) {
+            *node = json!({ "type": "object", "nullable": true, "description": format!("Recursive {def_name}") });
+            break;
+        }
+
+        if let Some(def) = definitions.get(&def_name) {
+            *node = def.clone();
+            ref_path.push(def_name);
+            pushed += 1;
+            // Loop continues to check if the new node is also a ref
         } else {
-            break; // No ref found, safe to proceed
+            *node = json!({ "type": "object", "description": "Unresolvable reference" });
+            break;
         }
     }
 
@@ -104,32 +255,231 @@ fn process_schema_node(node: &mut Value, definitions: &Map<String, Value>, depth
                 }
             }
 
+            // Gemini has no `const`; express it as a single-value enum so
+            // tagged-enum discriminants (e.g. `"op": "get"`) survive.
+            if let Some(value) = map.remove("const") {
+                map.insert("enum".to_string(), json!([value]));
+            }
+
+            // Keep `enum` constraints. Null moves to `nullable`, and a missing
+            // type is inferred for all-string enums.
+            if let Some(Value::Array(values)) = map.get_mut("enum") {
+                let had_null = values.iter().any(Value::is_null);
+                values.retain(|v| !v.is_null());
+                let all_strings = values.iter().all(Value::is_string);
+                if had_null {
+                    map.insert("nullable".to_string(), json!(true));
+                }
+                if all_strings && !map.contains_key("type") {
+                    map.insert("type".to_string(), json!("string"));
+                }
+            }
+
             // Recurse into properties
             if let Some(Value::Object(props)) = map.get_mut("properties") {
                 for val in props.values_mut() {
-                    process_schema_node(val, definitions, depth + 1);
+                    process_schema_node(val, definitions, ref_path);
                 }
             }
             
             // Recurse into items (for arrays)
             if let Some(val) = map.get_mut("items") {
-                process_schema_node(val, definitions, depth + 1);
+                process_schema_node(val, definitions, ref_path);
             }
 
             // Recurse into combinators
             for key in ["allOf", "anyOf", "oneOf"] {
                 if let Some(Value::Array(arr)) = map.get_mut(key) {
                     for item in arr.iter_mut() {
-                        process_schema_node(item, definitions, depth + 1);
+                        process_schema_node(item, definitions, ref_path);
                     }
                 }
             }
+
+            // `Option<T>` where T is a $ref/enum becomes `anyOf: [T, {type: null}]`.
+            // Collapse it (after the variants are cleaned) to T + nullable so the
+            // inner enum/required survive intact.
+            collapse_nullable_variant(map);
+
+            // `required` may only name declared properties; drop stale entries
+            // (and the list itself if nothing is left) rather than the constraint.
+            let declared: Option<Vec<String>> = map.get("properties")
+                .and_then(Value::as_object)
+                .map(|props| props.keys().cloned().collect());
+            if let Some(Value::Array(required)) = map.get_mut("required") {
+                if let Some(declared) = &declared {
+                    required.retain(|r| r.as_str().is_some_and(|name| declared.iter().any(|d| d == name)));
+                }
+                if required.is_empty() {
+                    map.remove("required");
+                }
+            }
         }
         Value::Array(arr) => {
             for item in arr.iter_mut() {
-                process_schema_node(item, definitions, depth + 1);
+                process_schema_node(item, definitions, ref_path);
             }
         }
         _ => {}
     }
-}
\ No newline at end of file
+
+    ref_path.truncate(ref_path.len() - pushed);
+}
+
+/// Replace `anyOf`/`oneOf: [schema, {"type": "null"}]` with `schema` marked
+/// nullable, keeping any sibling description.
+fn collapse_nullable_variant(map: &mut Map<String, Value>) {
+    for key in ["anyOf", "oneOf"] {
+        let Some(Value::Array(variants)) = map.get(key) else { continue };
+        if variants.len() != 2 {
+            continue;
+        }
+        let is_null = |v: &Value| v.get("type") == Some(&json!("null"));
+        let Some(inner) = variants.iter().find(|v| !is_null(v)).cloned() else { continue };
+        if !variants.iter().any(is_null) {
+            continue;
+        }
+
+        map.remove(key);
+        if let Value::Object(inner) = inner {
+            for (k, v) in inner {
+                map.entry(k).or_insert(v);
+            }
+        }
+        map.insert("nullable".to_string(), json!(true));
+        return;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemars::{schema_for, JsonSchema};
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    enum Status { Active, Closed }
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Project {
+        name: String,
+        status: Status,
+        previous: Option<Status>,
+    }
+
+    #[test]
+    fn clean_schema_keeps_enum_and_required() {
+        let schema = clean_schema(schema_for!(Project)).unwrap();
+        let props = &schema["properties"];
+        assert_eq!(props["status"]["enum"], json!(["active", "closed"]));
+        assert_eq!(props["status"]["type"], "string");
+        assert_eq!(props["previous"]["enum"], json!(["active", "closed"]));
+        assert_eq!(props["previous"]["nullable"], true);
+        assert_eq!(schema["required"], json!(["name", "status"]));
+        assert!(!schema.to_string().contains("$ref"), "{schema}");
+    }
+
+    #[test]
+    fn clean_schema_keeps_tagged_enum_discriminants() {
+        let schema = clean_schema(schema_for!(crate::core::dsl::LogicOp)).unwrap();
+        let tags: Vec<&Value> = schema["oneOf"].as_array().unwrap().iter()
+            .map(|variant| &variant["properties"]["op"]["enum"][0])
+            .collect();
+        assert!(tags.iter().any(|t| *t == "get"), "{tags:?}");
+        assert!(tags.iter().any(|t| *t == "subtract"));
+    }
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct L1 { l2: L2 }
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct L2 { l3: L3 }
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct L3 { l4: L4 }
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct L4 { l5: L5 }
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct L5 { leaf: f64 }
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Tree { children: Vec<Tree> }
+
+    #[test]
+    fn deep_acyclic_refs_are_expanded_in_full() {
+        let schema = clean_schema(schema_for!(L1)).unwrap();
+        let leaf = &schema["properties"]["l2"]["properties"]["l3"]["properties"]["l4"]["properties"]["l5"]["properties"]["leaf"];
+        assert_eq!(leaf["type"], "number", "{schema}");
+        assert!(!schema.to_string().contains("Recursive"));
+    }
+
+    #[test]
+    fn recursive_refs_are_truncated_once() {
+        let schema = clean_schema(schema_for!(Tree)).unwrap();
+        // The root isn't a `$ref`, so the first nested `Tree` is expanded and its own children are cut.
+        let child = &schema["properties"]["children"]["items"];
+        assert_eq!(child["required"], json!(["children"]), "{schema}");
+        let grandchild = &child["properties"]["children"]["items"];
+        assert_eq!(grandchild, &json!({ "type": "object", "nullable": true, "description": "Recursive Tree" }), "{schema}");
+    }
+
+    #[test]
+    fn sample_from_schema_fills_nested_objects_and_arrays() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "overhead_rate": { "type": "number", "minimum": 1.5 },
+                "owner": { "type": "object", "properties": { "name": { "type": "string" }, "active": { "type": "boolean" } } },
+                "projects": {
+                    "type": "array",
+                    "items": { "type": "object", "properties": { "status": { "enum": ["open", "closed"] }, "hours": { "type": ["integer", "null"] } } }
+                },
+                "tags": { "type": "array" }
+            }
+        });
+        let sample = sample_from_schema(&schema);
+        assert_eq!(sample, json!({
+            "overhead_rate": 1.5,
+            "owner": { "name": "", "active": false },
+            "projects": [{ "status": "open", "hours": 0 }],
+            "tags": []
+        }));
+        assert_eq!(validate_value(&sample, &schema), Ok(()));
+    }
+
+    fn violations(value: Value, schema: &Value) -> Vec<String> {
+        validate_value(&value, schema).err().unwrap_or_default().iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn validate_value_reports_each_violation_with_its_path() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name", "status"],
+            "properties": {
+                "name": { "type": "string" },
+                "status": { "type": "string", "enum": ["active", "closed"] },
+                "owner": { "type": "string", "nullable": true },
+                "projects": { "type": "array", "items": { "type": "object", "properties": { "revenue": { "type": "number" } } } }
+            }
+        });
+        assert!(violations(json!({ "name": "A", "status": "active", "owner": null }), &schema).is_empty());
+        assert_eq!(violations(json!({ "name": 3, "status": "active" }), &schema), ["/name: expected string, got number"]);
+        assert_eq!(violations(json!({ "status": "closed" }), &schema), ["/name: missing required property"]);
+        assert_eq!(
+            violations(json!({ "name": "A", "status": "paused" }), &schema),
+            [r#"/status: expected one of ["active","closed"], got "paused""#]
+        );
+        assert_eq!(
+            violations(json!({ "projects": [{ "revenue": 1 }, { "revenue": "x" }] }), &schema),
+            ["/name: missing required property", "/status: missing required property", "/projects/1/revenue: expected number, got string"]
+        );
+        assert_eq!(violations(json!([]), &schema), ["/: expected object, got array"]);
+    }
+}
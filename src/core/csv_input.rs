@@ -0,0 +1,131 @@
+use super::dsl::AppProgram;
+use super::runtime::Runtime;
+use crate::error::MetaError;
+use serde_json::{json, Map, Value};
+use std::io::Read;
+
+/// Parse CSV rows into JSON objects keyed by header. Numeric cells become
+/// numbers, empty cells become `null`, everything else stays a string.
+pub fn parse_csv(reader: impl Read) -> Result<Vec<Value>, MetaError> {
+    rows(reader)?.collect()
+}
+
+/// The rows of a CSV document, parsed lazily so one malformed record (e.g. a
+/// missing column) is an error for that row alone.
+fn rows(reader: impl Read) -> Result<impl Iterator<Item = Result<Value, MetaError>>, MetaError> {
+    let mut rdr = csv::Reader::from_reader(reader);
+    let headers = rdr.headers()
+        .map_err(|e| MetaError::ValidationFailed(format!("CSV header error: {e}")))?
+        .clone();
+
+    Ok(rdr.into_records().enumerate().map(move |(i, record)| {
+        let record = record.map_err(|e| MetaError::ValidationFailed(format!("CSV row {i}: {e}")))?;
+        let row: Map<String, Value> = headers.iter()
+            .zip(record.iter())
+            .map(|(h, cell)| (h.to_string(), coerce_cell(cell)))
+            .collect();
+        Ok(Value::Object(row))
+    }))
+}
+
+/// Outcome of one CSV row: its output, or the row index and the error it hit.
+pub type RowResult = Result<Value, (usize, MetaError)>;
+
+/// Run `program` once per CSV row and collect the results in row order.
+///
+/// With `continue_on_error`, a failing or malformed row is logged and recorded in place as
+/// `Err((index, error))` instead of aborting the batch. Without it, the first
+/// failing row returns an error naming its index.
+pub fn run_csv(program: &AppProgram, reader: impl Read, continue_on_error: bool) -> Result<Vec<RowResult>, MetaError> {
+    let mut outputs = Vec::new();
+
+    for (i, row) in rows(reader)?.enumerate() {
+        match row.and_then(|row| Runtime::execute(program, row)) {
+            Ok(output) => outputs.push(Ok(output)),
+            Err(e) if continue_on_error => {
+                log::warn!("CSV row {i} failed: {e}");
+                outputs.push(Err((i, e)));
+            }
+            Err(MetaError::RuntimeError { kind, path, step_id, step_description, message }) => {
+                let message = format!("CSV row {i}: {message}");
+                return Err(MetaError::RuntimeError { kind, path, step_id, step_description, message });
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(outputs)
+}
+
+fn coerce_cell(cell: &str) -> Value {
+    let trimmed = cell.trim();
+    if trimmed.is_empty() {
+        return Value::Null;
+    }
+    match trimmed.parse::<f64>() {
+        Ok(n) if n.is_finite() => json!(n),
+        _ => json!(cell),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dsl::AppDefinition;
+
+    const CSV: &str = "name,revenue,costs\nA,100,40\nB,50,n/a\nC, 70 ,\n";
+
+    fn profit_program() -> AppProgram {
+        AppProgram {
+            definition: AppDefinition {
+                name: "Profit".into(),
+                description: String::new(),
+                input_schema: json!({}),
+                output_schema: json!({ "type": "object", "properties": { "profit": { "type": "number" } } }),
+            },
+            steps: serde_json::from_value(json!([{
+                "id": "profit",
+                "description": "Revenue minus costs",
+                "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" },
+                "output_path": "/profit"
+            }])).unwrap(),
+            examples: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parse_csv_coerces_cells() {
+        let rows = parse_csv(CSV.as_bytes()).unwrap();
+        assert_eq!(rows[0], json!({ "name": "A", "revenue": 100.0, "costs": 40.0 }));
+        assert_eq!(rows[1]["costs"], json!("n/a"));
+        assert_eq!(rows[2], json!({ "name": "C", "revenue": 70.0, "costs": null }));
+    }
+
+    #[test]
+    fn run_csv_reports_bad_rows_by_index() {
+        let csv = "name,revenue,costs\nA,100,40\nB,50,n/a\nC,70,20\n";
+        let results = run_csv(&profit_program(), csv.as_bytes(), true).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &json!({ "profit": 60.0 }));
+        assert!(matches!(&results[1], Err((1, MetaError::RuntimeError { .. }))), "{:?}", results[1]);
+        assert_eq!(results[2].as_ref().unwrap(), &json!({ "profit": 50.0 }));
+    }
+
+    #[test]
+    fn run_csv_reports_malformed_records_by_index() {
+        let csv = "name,revenue,costs\nA,100,40\nB,50\nC,70,20\n";
+        let results = run_csv(&profit_program(), csv.as_bytes(), true).unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(matches!(&results[1], Err((1, MetaError::ValidationFailed(_)))), "{:?}", results[1]);
+        assert_eq!(results[2].as_ref().unwrap(), &json!({ "profit": 50.0 }));
+
+        let err = run_csv(&profit_program(), csv.as_bytes(), false).unwrap_err();
+        assert!(err.to_string().contains("CSV row 1: "), "{err}");
+    }
+
+    #[test]
+    fn run_csv_stops_at_the_first_bad_row_by_default() {
+        let csv = "name,revenue,costs\nA,100,40\nB,50,n/a\nC,70,20\n";
+        let err = run_csv(&profit_program(), csv.as_bytes(), false).unwrap_err();
+        assert!(err.to_string().contains("CSV row 1: "), "{err}");
+    }
+}
@@ -0,0 +1,38 @@
+use super::dsl::DateUnit;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+
+/// Parse an ISO-8601 date or date-time. Values without an offset
+/// (`2024-03-01`, `2024-03-01T09:30:00`) are taken as UTC; a bare date is midnight.
+pub fn parse_iso(text: &str) -> Option<DateTime<FixedOffset>> {
+    let text = text.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Some(dt);
+    }
+    let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f"))
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))?;
+    Some(naive.and_utc().fixed_offset())
+}
+
+/// `end - start` in `unit`, fractional for partial units.
+pub fn diff(start: DateTime<FixedOffset>, end: DateTime<FixedOffset>, unit: DateUnit) -> f64 {
+    let millis = end.signed_duration_since(start).num_milliseconds() as f64;
+    let per_unit = match unit {
+        DateUnit::Days => 86_400_000.0,
+        DateUnit::Hours => 3_600_000.0,
+        DateUnit::Minutes => 60_000.0,
+    };
+    millis / per_unit
+}
+
+/// Render `dt` with a strftime `format`. Unknown specifiers are an error rather
+/// than the panic chrono would raise while formatting.
+pub fn format(dt: DateTime<FixedOffset>, format: &str) -> Result<String, String> {
+    let items: Vec<Item> = StrftimeItems::new(format).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(format!("Invalid date format '{format}'"));
+    }
+    Ok(dt.format_with_items(items.into_iter()).to_string())
+}
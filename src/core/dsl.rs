@@ -1,6 +1,9 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
+/// A literal value. Untagged, so the JSON shape picks the variant: arrays and
+/// objects can never match the scalar variants.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum ConstantValue {
@@ -8,38 +11,143 @@ pub enum ConstantValue {
     Number(f64),
     Bool(bool),
     Null,
+    Array(Vec<serde_json::Value>),
+    Object(serde_json::Map<String, serde_json::Value>),
+}
+
+impl ConstantValue {
+    pub fn to_value(&self) -> serde_json::Value {
+        match self {
+            ConstantValue::String(s) => serde_json::json!(s),
+            ConstantValue::Number(n) => serde_json::json!(n),
+            ConstantValue::Bool(b) => serde_json::json!(b),
+            ConstantValue::Null => serde_json::Value::Null,
+            ConstantValue::Array(items) => serde_json::Value::Array(items.clone()),
+            ConstantValue::Object(fields) => serde_json::Value::Object(fields.clone()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum MathOp { Add, Subtract, Multiply, Divide }
 
+/// Result of dividing by zero in `Divide` and `Calculate`. JSON numbers can't
+/// be infinite, so `Infinity` is written as the string sentinel "Infinity" or
+/// "-Infinity" (and 0/0 as null). Output coercion keeps the sentinel in number
+/// fields; reading it as an operand of another numeric op is a `NonFinite` error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ZeroPolicy { Error, Zero, Null, Infinity }
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FormatVariable {
     #[schemars(description = "The placeholder name in the template (without braces).")]
     pub key: String,
     #[schemars(description = "The path to the data value.")]
     pub path: String,
+    #[serde(default)]
+    #[schemars(description = "Optional number formatting. Ignored for non-numeric values.")]
+    pub format: Option<NumberFormat>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NumberFormat {
+    #[schemars(description = "Fixed number of decimal places (rounded).")]
+    pub decimals: Option<u32>,
+    #[serde(default)]
+    #[schemars(description = "Group thousands with commas, e.g. 1,234.")]
+    pub thousands_separator: bool,
+    #[schemars(description = "Text placed before the number (after any minus sign), e.g. '$'.")]
+    pub prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ObjectField {
+    #[schemars(description = "The key in the resulting object.")]
+    pub key: String,
+    #[schemars(description = "The path to the data value.")]
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ItemStep {
+    #[schemars(description = "Field written on the current item.")]
+    pub output_field: String,
+    #[schemars(description = "Operation evaluated per item. Bare names (no leading '/') read fields of the current item.")]
+    pub operation: LogicOp,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "op", rename_all = "snake_case")]
 #[schemars(description = "An atomic operation. Select exactly one 'op'.")]
 pub enum LogicOp {
-    #[schemars(description = "Read a value from the state.")]
-    Get { path: String },
+    #[schemars(description = "Read a value from the state. If 'default' is given, a missing path yields it instead of an error.")]
+    Get {
+        path: String,
+        #[serde(default)]
+        default: Option<ConstantValue>,
+    },
+
+    #[schemars(description = "Return the first path that resolves to a non-null value, else 'default'.")]
+    Coalesce { paths: Vec<String>, default: ConstantValue },
     
-    #[schemars(description = "Set a constant value.")]
+    #[schemars(description = "Set a constant value: a string, number, boolean, null, or a literal array/object.")]
     Constant { value: ConstantValue },
     
-    #[schemars(description = "Extract a field from a list of objects.")]
-    Pluck { path: String, key: String },
+    #[schemars(description = "Extract a field from a list of objects. List-op fields accept plain keys, dotted paths ('metrics.revenue') or pointers ('/metrics/revenue').")]
+    Pluck {
+        path: String,
+        key: String,
+        #[serde(default)]
+        #[schemars(description = "Value used for items where 'key' (or any parent on a dotted path) is missing or null. Without it such items yield null.")]
+        default: Option<ConstantValue>,
+    },
+
+    #[schemars(description = "Read one field across every element of a list using a single '*' wildcard, e.g. '/projects/*/revenue'. Returns the found values as an array (elements without the field are skipped, so no match gives []).")]
+    Collect { path: String },
+
+    #[schemars(description = "Convert a number or numeric string (e.g. \"15000\") to a number. Errors if it can't be parsed.")]
+    ParseNumber { path: String },
+
+    #[schemars(description = "Force the value at 'path' to a JSON type. string: numbers in plain decimal (never scientific notation), booleans as \"true\"/\"false\", null as \"\", lists/objects as JSON text. number: numeric strings are parsed, true/false become 1/0. bool: non-zero numbers are true; \"true\"/\"1\"/\"yes\" and \"false\"/\"0\"/\"no\"/\"\" (any case); null is false. Other casts error.")]
+    Cast { path: String, target: CastType },
+
+    #[schemars(description = "Map the value at 'path' through a static 'table' (e.g. {\"US\": \"United States\"}). The value is stringified to form the key (numbers without a fraction as integers, e.g. 3.0 -> \"3\"). A miss returns 'default', or errors when no default is given.")]
+    Lookup {
+        path: String,
+        table: serde_json::Map<String, serde_json::Value>,
+        #[serde(default)]
+        default: Option<ConstantValue>,
+    },
 
     // Math
     Add { a: String, b: String },
     Subtract { a: String, b: String },
     Multiply { a: String, b: String },
-    Divide { a: String, b: String },
+    Divide {
+        a: String,
+        b: String,
+        #[serde(default)]
+        #[schemars(description = "What dividing by zero yields; `infinity` writes the string \"Infinity\" or \"-Infinity\". Default: error.")]
+        on_zero: Option<ZeroPolicy>,
+    },
+    #[schemars(description = "numerator / denominator * 100. Yields 0.0 (no error) when the denominator is zero.")]
+    Percentage { numerator: String, denominator: String },
+    #[schemars(description = "numerator / denominator. Yields 0.0 (no error) when the denominator is zero.")]
+    Ratio { numerator: String, denominator: String },
+    #[schemars(description = "Remainder of a / b (sign follows 'a'). Errors when b is zero.")]
+    Modulo { a: String, b: String },
+    #[schemars(description = "Raise 'base' to 'exponent'.")]
+    Power { base: String, exponent: String },
+    #[schemars(description = "Absolute value of the number at 'path'.")]
+    Abs { path: String },
+    #[schemars(description = "The number at 'path' with its sign flipped.")]
+    Negate { path: String },
+    #[schemars(description = "-1, 0 or 1 depending on the sign of the number at 'path'.")]
+    Sign { path: String },
+    #[schemars(description = "Bound a number into [min, max].")]
+    Clamp { path: String, min: f64, max: f64 },
     
     #[schemars(description = "Math on list items.")]
     Calculate {
@@ -48,13 +156,71 @@ pub enum LogicOp {
         operator: MathOp,
         a_field: String,
         b_field: String,
+        #[serde(default)]
+        #[schemars(description = "What a 'divide' by zero yields; `infinity` writes the string \"Infinity\" or \"-Infinity\". Default: zero.")]
+        on_zero: Option<ZeroPolicy>,
+    },
+
+    #[schemars(description = "Cumulative sum: walk the list in order and write the running total of 'field' into 'output_field' on each item. Items without a numeric 'field' carry the total unchanged; non-object items are left as-is. Combine with sort for e.g. cumulative revenue by date.")]
+    RunningTotal {
+        list_path: String,
+        field: String,
+        output_field: String,
     },
 
+    #[schemars(description = "Min-max scale 'field' across the list into 'output_field' on each item: (x - min) / (max - min), so values land in 0..1. When every value is equal the result is 0; items without a numeric 'field' get null.")]
+    Normalize {
+        list_path: String,
+        field: String,
+        output_field: String,
+    },
+
+    #[schemars(description = "Run several operations per list item, writing each result to a field on the item. Later steps can read earlier output fields. The current item is also available at '/item'.")]
+    MapItems {
+        list_path: String,
+        steps: Vec<ItemStep>,
+    },
+
+    #[schemars(description = "Evaluate 'ops' in order within one step and return the last result. The result of ops[0] is readable by later ops as '/_0', ops[1] as '/_1', and so on, e.g. (revenue - costs) * margin = [subtract(/revenue, /costs), multiply(/_0, /margin)]. A nested chain numbers its own results from '/_0' again, shadowing the outer ones.")]
+    Chain { ops: Vec<LogicOp> },
+
     // Aggregations
     Sum { list_path: String, field: Option<String> },
+    #[schemars(description = "Sum several numeric fields in one pass. Returns {<field>: total} for each of 'fields' (e.g. {\"revenue\": 1500, \"costs\": 300}); non-numeric or missing values are skipped, so an empty list gives 0 for every field.")]
+    SumFields { list_path: String, fields: Vec<String> },
+    #[schemars(description = "Multiply all values. An empty list yields 1.")]
+    Product { list_path: String, field: Option<String> },
+    #[schemars(description = "Fold the numeric values (of 'field' if given) left-to-right: acc = acc <operator> value, starting from 'initial'. Non-numeric items are skipped; dividing by a zero value errors.")]
+    Reduce {
+        list_path: String,
+        field: Option<String>,
+        operator: MathOp,
+        initial: f64,
+    },
     Count { list_path: String },
+    #[schemars(description = "Number of items whose numeric value (of 'field' if given) satisfies 'operator' against 'value'. Same predicate as filter_numeric; an empty list yields 0.")]
+    CountWhere {
+        list_path: String,
+        field: Option<String>,
+        operator: CmpOp,
+        value: f64,
+    },
+    #[schemars(description = "Unique values (of 'field' if given), in first-seen order. Items missing 'field' are skipped.")]
+    Distinct { list_path: String, field: Option<String> },
+    #[schemars(description = "Number of unique values (of 'field' if given).")]
+    DistinctCount { list_path: String, field: Option<String> },
     Min { list_path: String, field: Option<String> },
     Max { list_path: String, field: Option<String> },
+    #[schemars(description = "Median value; the two middle values are averaged for even counts. Empty lists yield null.")]
+    Median { list_path: String, field: Option<String> },
+    #[schemars(description = "Standard deviation. population=true divides by n, false (sample) by n-1. Yields null when there are too few values.")]
+    StdDev { list_path: String, field: Option<String>, population: bool },
+    #[schemars(description = "Count values per bucket. N boundaries give N+1 counts: values below the first boundary, then one per [boundary, next boundary) range, the last being the overflow (>= the last boundary). A value equal to a boundary counts in the upper bucket. Boundaries are sorted before use.")]
+    Histogram { list_path: String, field: Option<String>, buckets: Vec<f64> },
+    #[schemars(description = "Weighted mean: sum(value * weight) / sum(weight). Fields may be dotted paths; items missing either number are skipped. Yields null when the total weight is 0.")]
+    WeightedAverage { list_path: String, value_field: String, weight_field: String },
+    #[schemars(description = "Trailing moving average over a window of values (window >= 1). Returns one entry per numeric value; the first window-1 entries are null because the window is not yet full.")]
+    MovingAverage { list_path: String, field: Option<String>, window: usize },
 
     // Logic
     FilterNumeric {
@@ -64,12 +230,177 @@ pub enum LogicOp {
         value: f64
     },
     
+    #[schemars(description = "Remove items whose 'field' is null or missing (or, without 'field', items that are null). Use before sum/average to keep nulls from missing data out.")]
+    DropNulls { list_path: String, field: Option<String> },
+
+    #[schemars(description = "Keep items whose numeric field lies between 'min' and 'max'. inclusive=true keeps values equal to either bound; false excludes both bounds.")]
+    FilterRange {
+        list_path: String,
+        field: Option<String>,
+        min: f64,
+        max: f64,
+        inclusive: bool,
+    },
+
+    #[schemars(description = "Group list items by 'key_field' and aggregate 'aggregate_field' per group. Returns [{<key_field>: key, value: number}] in first-seen order; items missing 'key_field' are grouped under a null key.")]
+    GroupBy {
+        list_path: String,
+        key_field: String,
+        #[schemars(description = "Numeric field to aggregate. Ignored for 'count'.")]
+        aggregate_field: Option<String>,
+        aggregation: AggKind,
+    },
+
+    #[schemars(description = "Cross-tab a list: one row per distinct 'row_field' value (first-seen order), with one column per distinct 'col_field' value (stringified) holding the aggregated 'value_field'. Returns [{<row_field>: key, <col>: number, ...}]. Every row has every column; empty cells are 0 for 'sum'/'count' and null otherwise. Items missing 'col_field' are skipped.")]
+    Pivot {
+        list_path: String,
+        row_field: String,
+        col_field: String,
+        #[schemars(description = "Numeric field to aggregate. Ignored for 'count'.")]
+        value_field: Option<String>,
+        aggregation: AggKind,
+    },
+
+    #[schemars(description = "Turn a list of objects into an object keyed by each item's 'key_field' (stringified, e.g. 3 -> \"3\"). Later duplicates overwrite earlier ones; items missing 'key_field' are skipped.")]
+    IndexBy { list_path: String, key_field: String },
+
+    // Boolean
+    #[schemars(description = "True if every condition is truthy (short-circuits). Truthy: true, non-zero numbers, non-empty strings/arrays/objects.")]
+    And { conditions: Vec<LogicOp> },
+
+    #[schemars(description = "True if any condition is truthy (short-circuits).")]
+    Or { conditions: Vec<LogicOp> },
+
+    #[schemars(description = "Negate the truthiness of a condition.")]
+    Not { condition: Box<LogicOp> },
+
+    #[schemars(description = "True if the values at 'a' and 'b' are deeply equal. Numbers compare by value (1 equals 1.0); object key order is ignored.")]
+    Equals { a: String, b: String },
+
+    #[schemars(description = "True if 'haystack' contains 'needle'. An array haystack checks membership with 'equals' semantics; a string haystack checks for a substring (a numeric or bool needle is matched as text, e.g. 3 -> \"3\"). Errors if 'haystack' is neither.")]
+    Contains { haystack: String, needle: String },
+
+    #[schemars(description = "List where the values at 'a' and 'b' differ: [{path, a, b}] with 'path' a JSON Pointer relative to the compared values (\"\" for the values themselves). A side is omitted when the key or index exists only in the other value. Empty when equal (see 'equals').")]
+    JsonDiff { a: String, b: String },
+
+    #[schemars(description = "Keep list items whose string field matches. Items whose field isn't a string are dropped.")]
+    FilterString {
+        list_path: String,
+        field: Option<String>,
+        operator: StrOp,
+        value: String,
+        #[serde(default)]
+        #[schemars(description = "Compare case-insensitively. Defaults to false.")]
+        ignore_case: bool,
+    },
+
+    #[schemars(description = "Write a 1-based rank by 'field' into 'output_field' on each item, keeping the list order (no prior sort needed). Values compare like sort; descending=true gives the largest value rank 1. Ties share a rank and the next rank skips (1, 2, 2, 4).")]
+    Rank {
+        list_path: String,
+        field: String,
+        output_field: String,
+        descending: bool,
+    },
+
+    #[schemars(description = "Stable sort by 'field'. Numbers sort numerically, strings lexicographically; numbers come before strings, even when descending.")]
     Sort {
         list_path: String,
         field: String,
         descending: bool,
+        #[serde(default)]
+        #[schemars(description = "Secondary field (always ascending) used to break ties.")]
+        then_by: Option<String>,
     },
     
+    #[schemars(description = "First 'count' items of a list (all items if the list is shorter).")]
+    Take { list_path: String, count: usize },
+
+    #[schemars(description = "Items from 'start' (inclusive) to 'end' (exclusive, defaults to the end). Out-of-range indices are clamped.")]
+    Slice { list_path: String, start: usize, end: Option<usize> },
+
+    #[schemars(description = "Reverse the order of a list.")]
+    Reverse { list_path: String },
+
+    #[schemars(description = "Flatten nested arrays by one level, or 'depth' levels if given (e.g. after plucking a list field). Non-array elements are kept as-is.")]
+    Flatten {
+        path: String,
+        #[serde(default)]
+        depth: Option<usize>,
+    },
+
+    #[schemars(description = "Concatenate the arrays at 'paths', in order, into one list. Errors if any path is not an array.")]
+    MergeArrays { paths: Vec<String> },
+
+    #[schemars(description = "Split the list into consecutive sub-lists of at most 'size' items (size >= 1); the last chunk may be smaller.")]
+    Chunk { list_path: String, size: usize },
+
+    #[schemars(description = "Pair two lists by index into objects {left_key: left item, right_key: right item}. The result is as long as the shorter list.")]
+    Zip { left_path: String, right_path: String, left_key: String, right_key: String },
+
+    #[schemars(description = "Copy of the list at 'list_path' with 'value' added to the end.")]
+    Append { list_path: String, value: ConstantValue },
+
+    #[schemars(description = "Pick one element by index (0 = first, -1 = last), optionally extracting 'field' from it. Out of range yields null.")]
+    Nth { list_path: String, index: i64, field: Option<String> },
+
+    #[schemars(description = "Assemble an object from values in the state. Missing paths become null.")]
+    BuildObject { fields: Vec<ObjectField> },
+
+    #[schemars(description = "Merge the objects at 'paths' into one; later paths win on key conflicts. With deep=true nested objects are merged recursively, otherwise a conflicting key is replaced wholesale. Errors if any path is not an object.")]
+    MergeObjects { paths: Vec<String>, deep: bool },
+
+    #[schemars(description = "Rename keys of the object at 'path' per 'mapping' (old name -> new name). Keys not in 'mapping' are kept; a renamed key overwrites an existing key of the same name. Non-object values pass through unchanged.")]
+    RenameKeys { path: String, mapping: BTreeMap<String, String> },
+
+    #[schemars(description = "Convert the keys of the object at 'path' to snake_case, camelCase or kebab-case (e.g. 'totalRevenue' <-> 'total_revenue'). With recursive=true nested objects, including those inside arrays, are converted too. Keys that collide after conversion keep the last one in key order. Non-object values pass through unchanged.")]
+    TransformKeys {
+        path: String,
+        style: KeyStyle,
+        #[serde(default)]
+        #[schemars(description = "Also convert nested objects. Defaults to false.")]
+        recursive: bool,
+    },
+
+    // Strings
+    #[schemars(description = "Uppercase the string at 'path'. Errors if it is not a string.")]
+    ToUpper { path: String },
+    #[schemars(description = "Lowercase the string at 'path'. Errors if it is not a string.")]
+    ToLower { path: String },
+    #[schemars(description = "Strip leading and trailing whitespace from the string at 'path'.")]
+    Trim { path: String },
+    #[schemars(description = "Characters from 'start' (0-based, counted in characters, not bytes), up to 'length' characters or to the end. Out-of-range positions yield a shorter or empty string.")]
+    Substring {
+        path: String,
+        start: usize,
+        #[serde(default)]
+        length: Option<usize>,
+    },
+
+    // Regex
+    #[cfg(feature = "regex")]
+    #[schemars(description = "Capture 'group' (0 = whole match) of the first match of 'pattern' in the string at 'path'. No match, or a group that didn't participate, yields null.")]
+    RegexExtract { path: String, pattern: String, group: usize },
+
+    #[cfg(feature = "regex")]
+    #[schemars(description = "True if 'pattern' matches anywhere in the string at 'path'.")]
+    RegexMatch { path: String, pattern: String },
+
+    #[cfg(feature = "regex")]
+    #[schemars(description = "Replace every match of 'pattern' in the string at 'path' with 'replacement' ('$1' refers to capture groups).")]
+    RegexReplace { path: String, pattern: String, replacement: String },
+
+    // Dates
+    #[cfg(feature = "chrono")]
+    #[schemars(description = "Time from the date at 'start_path' to the one at 'end_path' in 'unit' (negative if end is earlier; fractional for partial units). Dates are ISO-8601: '2024-03-01', '2024-03-01T09:30:00' or with an offset ('...Z', '+02:00').")]
+    DateDiff { start_path: String, end_path: String, unit: DateUnit },
+
+    #[cfg(feature = "chrono")]
+    #[schemars(description = "Reformat the ISO-8601 date at 'path' with a strftime 'format', e.g. '%Y-%m-%d' or '%d %b %Y'.")]
+    DateFormat { path: String, format: String },
+
+    #[schemars(description = "Render 'item_template' once per list item and join the results with 'separator'. Placeholders name item fields, e.g. '{name} (${profit})'; missing fields render as empty text.")]
+    FormatList { list_path: String, item_template: String, separator: String },
+
     #[schemars(description = "Create a formatted string.")]
     FormatString {
         #[schemars(description = "Template like 'Hello {name}'.")]
@@ -79,10 +410,146 @@ pub enum LogicOp {
     }
 }
 
+impl LogicOp {
+    /// The `op` tag this operation serializes with, e.g. `"format_string"`.
+    pub fn name(&self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|v| v.get("op").and_then(|op| op.as_str()).map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    /// State paths this operation reads from (operands, list paths, template variables).
+    /// Bare item field names (e.g. `Calculate`'s `a_field`) are not state paths and are skipped.
+    pub fn read_paths(&self) -> Vec<&str> {
+        match self {
+            LogicOp::Get { path, .. } => vec![path],
+            LogicOp::Coalesce { paths, .. }
+            | LogicOp::MergeArrays { paths }
+            | LogicOp::MergeObjects { paths, .. } => paths.iter().map(String::as_str).collect(),
+            LogicOp::Zip { left_path, right_path, .. } => vec![left_path, right_path],
+            LogicOp::Constant { .. } => vec![],
+            LogicOp::Collect { path } => vec![path.split("/*").next().unwrap_or(path)],
+            LogicOp::Pluck { path, .. }
+            | LogicOp::ParseNumber { path }
+            | LogicOp::Cast { path, .. }
+            | LogicOp::Lookup { path, .. }
+            | LogicOp::RenameKeys { path, .. }
+            | LogicOp::TransformKeys { path, .. }
+            | LogicOp::Flatten { path, .. }
+            | LogicOp::ToUpper { path }
+            | LogicOp::ToLower { path }
+            | LogicOp::Trim { path }
+            | LogicOp::Substring { path, .. }
+            | LogicOp::Clamp { path, .. }
+            | LogicOp::Abs { path }
+            | LogicOp::Negate { path }
+            | LogicOp::Sign { path } => vec![path],
+            LogicOp::Add { a, b }
+            | LogicOp::Subtract { a, b }
+            | LogicOp::Multiply { a, b }
+            | LogicOp::Divide { a, b, .. }
+            | LogicOp::Modulo { a, b }
+            | LogicOp::Equals { a, b }
+            | LogicOp::JsonDiff { a, b } => vec![a, b],
+            LogicOp::Contains { haystack, needle } => vec![haystack, needle],
+            #[cfg(feature = "regex")]
+            LogicOp::RegexExtract { path, .. }
+            | LogicOp::RegexMatch { path, .. }
+            | LogicOp::RegexReplace { path, .. } => vec![path],
+            #[cfg(feature = "chrono")]
+            LogicOp::DateDiff { start_path, end_path, .. } => vec![start_path, end_path],
+            #[cfg(feature = "chrono")]
+            LogicOp::DateFormat { path, .. } => vec![path],
+            LogicOp::Percentage { numerator, denominator }
+            | LogicOp::Ratio { numerator, denominator } => vec![numerator, denominator],
+            LogicOp::Power { base, exponent } => vec![base, exponent],
+            LogicOp::Calculate { list_path, a_field, b_field, .. } => {
+                let mut paths = vec![list_path.as_str()];
+                paths.extend([a_field, b_field].into_iter().filter(|f| f.starts_with('/')).map(String::as_str));
+                paths
+            }
+            LogicOp::Sum { list_path, .. }
+            | LogicOp::SumFields { list_path, .. }
+            | LogicOp::Product { list_path, .. }
+            | LogicOp::Count { list_path }
+            | LogicOp::CountWhere { list_path, .. }
+            | LogicOp::Reduce { list_path, .. }
+            | LogicOp::Append { list_path, .. }
+            | LogicOp::RunningTotal { list_path, .. }
+            | LogicOp::Normalize { list_path, .. }
+            | LogicOp::DropNulls { list_path, .. }
+            | LogicOp::Distinct { list_path, .. }
+            | LogicOp::DistinctCount { list_path, .. }
+            | LogicOp::Min { list_path, .. }
+            | LogicOp::Max { list_path, .. }
+            | LogicOp::Median { list_path, .. }
+            | LogicOp::StdDev { list_path, .. }
+            | LogicOp::MovingAverage { list_path, .. }
+            | LogicOp::WeightedAverage { list_path, .. }
+            | LogicOp::Histogram { list_path, .. }
+            | LogicOp::FilterNumeric { list_path, .. }
+            | LogicOp::FilterRange { list_path, .. }
+            | LogicOp::FilterString { list_path, .. }
+            | LogicOp::GroupBy { list_path, .. }
+            | LogicOp::Pivot { list_path, .. }
+            | LogicOp::IndexBy { list_path, .. }
+            | LogicOp::Sort { list_path, .. }
+            | LogicOp::Rank { list_path, .. }
+            | LogicOp::Take { list_path, .. }
+            | LogicOp::Slice { list_path, .. }
+            | LogicOp::Reverse { list_path }
+            | LogicOp::Chunk { list_path, .. }
+            | LogicOp::FormatList { list_path, .. }
+            | LogicOp::Nth { list_path, .. } => vec![list_path],
+            LogicOp::FormatString { variables, .. } => variables.iter().map(|v| v.path.as_str()).collect(),
+            LogicOp::BuildObject { fields } => fields.iter().map(|f| f.path.as_str()).collect(),
+            LogicOp::And { conditions } | LogicOp::Or { conditions } => {
+                conditions.iter().flat_map(|c| c.read_paths()).collect()
+            }
+            LogicOp::Not { condition } => condition.read_paths(),
+            LogicOp::Chain { ops } => ops.iter()
+                .flat_map(|op| op.read_paths())
+                .filter(|p| !is_chain_scratch(p))
+                .collect(),
+            LogicOp::MapItems { list_path, steps } => {
+                let mut paths = vec![list_path.as_str()];
+                paths.extend(
+                    steps.iter()
+                        .flat_map(|s| s.operation.read_paths())
+                        .filter(|p| p.starts_with('/') && *p != "/item" && !p.starts_with("/item/")),
+                );
+                paths
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum CmpOp { Gt, Lt, Eq, Gte, Lte }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StrOp { Eq, NotEq, Contains, StartsWith, EndsWith }
+
+#[cfg(feature = "chrono")]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DateUnit { Days, Hours, Minutes }
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CastType { String, Number, Bool }
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStyle { Snake, Camel, Kebab }
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AggKind { Sum, Count, Average, Min, Max }
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LogicStep {
     pub id: String,
@@ -105,4 +572,52 @@ pub struct AppDefinition {
 pub struct AppProgram {
     pub definition: AppDefinition,
     pub steps: Vec<LogicStep>,
-}
\ No newline at end of file
+    /// Known-good input/output pairs (from the passing QA tests), kept as
+    /// documentation and regression fixtures for saved programs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<ProgramExample>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProgramExample {
+    pub input: serde_json::Value,
+    pub output: serde_json::Value,
+}
+
+/// True for `Chain` scratch paths (`/_0`, `/_1/field`, ...), which only exist
+/// while the chain runs.
+pub(crate) fn is_chain_scratch(path: &str) -> bool {
+    let Some(rest) = path.strip_prefix("/_") else { return false };
+    let index = rest.split('/').next().unwrap_or_default();
+    !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn structured_constants_round_trip() {
+        for value in [json!([1, "two", null]), json!({ "US": "United States", "codes": [1, 2] }), json!([])] {
+            let constant: ConstantValue = serde_json::from_value(value.clone()).unwrap();
+            assert!(matches!(constant, ConstantValue::Array(_) | ConstantValue::Object(_)), "{value}");
+            assert_eq!(constant.to_value(), value);
+            assert_eq!(serde_json::to_value(&constant).unwrap(), value);
+        }
+        let scalar: ConstantValue = serde_json::from_value(json!("[1, 2]")).unwrap();
+        assert!(matches!(scalar, ConstantValue::String(_)));
+
+        let op: LogicOp = serde_json::from_value(json!({ "op": "constant", "value": { "a": [1] } })).unwrap();
+        assert!(matches!(op, LogicOp::Constant { value: ConstantValue::Object(_) }));
+    }
+
+    #[test]
+    fn programs_without_examples_still_load() {
+        let program: AppProgram = serde_json::from_value(json!({
+            "definition": { "name": "Old", "description": "", "input_schema": {}, "output_schema": {} },
+            "steps": []
+        })).unwrap();
+        assert!(program.examples.is_empty());
+    }
+}
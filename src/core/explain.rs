@@ -0,0 +1,367 @@
+use super::dsl::{AggKind, AppProgram, CastType, CmpOp, ConstantValue, KeyStyle, LogicOp, MathOp, StrOp, ZeroPolicy};
+
+impl AppProgram {
+    /// Render the program as plain-English pseudocode, one line per step, e.g.
+    /// `total_revenue = SUM of projects[].revenue`.
+    pub fn explain(&self) -> String {
+        let mut lines = vec![format!("# {}", self.definition.name)];
+        for step in &self.steps {
+            lines.push(format!("{} = {}", trim(&step.output_path), describe(&step.operation)));
+        }
+        lines.join("\n")
+    }
+}
+
+/// One-line description of a single operation (nested ops are described inline).
+pub fn describe(op: &LogicOp) -> String {
+    match op {
+        LogicOp::Get { path, default: None } => trim(path).to_string(),
+        LogicOp::Get { path, default: Some(d) } => format!("{} (default {})", trim(path), constant(d)),
+        LogicOp::Coalesce { paths, default } => {
+            let paths: Vec<&str> = paths.iter().map(|p| trim(p)).collect();
+            format!("first non-null of [{}] else {}", paths.join(", "), constant(default))
+        }
+        LogicOp::Constant { value } => constant(value),
+        LogicOp::Collect { path } => format!("all {}", trim(path)),
+        LogicOp::Pluck { path, key, default: None } => list_ref(path, Some(key)),
+        LogicOp::Pluck { path, key, default: Some(d) } => {
+            format!("{} (default {})", list_ref(path, Some(key)), constant(d))
+        }
+        LogicOp::ParseNumber { path } => format!("number({})", trim(path)),
+        LogicOp::Cast { path, target } => {
+            let target = match target {
+                CastType::String => "string",
+                CastType::Number => "number",
+                CastType::Bool => "bool",
+            };
+            format!("{}({})", target, trim(path))
+        }
+        LogicOp::Lookup { path, table, default } => {
+            let fallback = default.as_ref().map(|d| format!(" else {}", constant(d))).unwrap_or_default();
+            format!("lookup {} in table of {} entries{}", trim(path), table.len(), fallback)
+        }
+        LogicOp::Add { a, b } => format!("{} + {}", trim(a), trim(b)),
+        LogicOp::Subtract { a, b } => format!("{} - {}", trim(a), trim(b)),
+        LogicOp::Multiply { a, b } => format!("{} * {}", trim(a), trim(b)),
+        LogicOp::Divide { a, b, on_zero } => format!("{} / {}{}", trim(a), trim(b), zero_note(*on_zero)),
+        LogicOp::Percentage { numerator, denominator } => {
+            format!("{} / {} * 100 (0 if denominator is 0)", trim(numerator), trim(denominator))
+        }
+        LogicOp::Ratio { numerator, denominator } => {
+            format!("{} / {} (0 if denominator is 0)", trim(numerator), trim(denominator))
+        }
+        LogicOp::Modulo { a, b } => format!("{} mod {}", trim(a), trim(b)),
+        LogicOp::Power { base, exponent } => format!("{} ^ {}", trim(base), trim(exponent)),
+        LogicOp::Abs { path } => format!("|{}|", trim(path)),
+        LogicOp::Negate { path } => format!("-{}", trim(path)),
+        LogicOp::Sign { path } => format!("sign({})", trim(path)),
+        LogicOp::Clamp { path, min, max } => format!("clamp({}, {}, {})", trim(path), min, max),
+        LogicOp::Calculate { list_path, output_field, operator, a_field, b_field, on_zero } => format!(
+            "for each item in {}: {} = {} {} {}{}",
+            trim(list_path), output_field, trim(a_field), math_sym(operator), trim(b_field),
+            if matches!(operator, MathOp::Divide) { zero_note(*on_zero) } else { "" }
+        ),
+        LogicOp::RunningTotal { list_path, field, output_field } => format!(
+            "for each item in {}: {} = running total of {}",
+            trim(list_path), output_field, field
+        ),
+        LogicOp::Normalize { list_path, field, output_field } => format!(
+            "for each item in {}: {} = {} scaled to 0..1",
+            trim(list_path), output_field, field
+        ),
+        LogicOp::MapItems { list_path, steps } => {
+            let parts: Vec<String> = steps.iter()
+                .map(|s| format!("{} = {}", s.output_field, describe(&s.operation)))
+                .collect();
+            format!("for each item in {}: {}", trim(list_path), parts.join("; "))
+        }
+        LogicOp::Chain { ops } => {
+            let parts: Vec<String> = ops.iter().enumerate()
+                .map(|(i, op)| format!("_{} = {}", i, describe(op)))
+                .collect();
+            format!("chain({})", parts.join("; "))
+        }
+        LogicOp::Sum { list_path, field } => format!("SUM of {}", list_ref(list_path, field.as_deref())),
+        LogicOp::SumFields { list_path, fields } => format!("SUM of {}[].{{{}}}", trim(list_path), fields.join(", ")),
+        LogicOp::Product { list_path, field } => format!("PRODUCT of {}", list_ref(list_path, field.as_deref())),
+        LogicOp::Reduce { list_path, field, operator, initial } => format!(
+            "REDUCE {} with {} from {}",
+            list_ref(list_path, field.as_deref()), math_sym(operator), initial
+        ),
+        LogicOp::Count { list_path } => format!("COUNT of {}", trim(list_path)),
+        LogicOp::CountWhere { list_path, field, operator, value } => format!(
+            "COUNT of {} where {} {} {}",
+            trim(list_path), field.as_deref().unwrap_or("value"), cmp_sym(*operator), value
+        ),
+        LogicOp::Distinct { list_path, field } => format!("DISTINCT {}", list_ref(list_path, field.as_deref())),
+        LogicOp::DistinctCount { list_path, field } => {
+            format!("COUNT DISTINCT {}", list_ref(list_path, field.as_deref()))
+        }
+        LogicOp::Min { list_path, field } => format!("MIN of {}", list_ref(list_path, field.as_deref())),
+        LogicOp::Max { list_path, field } => format!("MAX of {}", list_ref(list_path, field.as_deref())),
+        LogicOp::Median { list_path, field } => format!("MEDIAN of {}", list_ref(list_path, field.as_deref())),
+        LogicOp::StdDev { list_path, field, population } => format!(
+            "{} STDDEV of {}",
+            if *population { "population" } else { "sample" },
+            list_ref(list_path, field.as_deref())
+        ),
+        LogicOp::Histogram { list_path, field, buckets } => {
+            let bounds: Vec<String> = buckets.iter().map(|b| b.to_string()).collect();
+            format!("HISTOGRAM of {} split at [{}]", list_ref(list_path, field.as_deref()), bounds.join(", "))
+        }
+        LogicOp::WeightedAverage { list_path, value_field, weight_field } => format!(
+            "WEIGHTED AVERAGE of {} by {}",
+            list_ref(list_path, Some(value_field)), weight_field
+        ),
+        LogicOp::MovingAverage { list_path, field, window } => {
+            format!("{}-item MOVING AVERAGE of {}", window, list_ref(list_path, field.as_deref()))
+        }
+        LogicOp::FilterNumeric { list_path, field, operator, value } => format!(
+            "items of {} where {} {} {}",
+            trim(list_path), field.as_deref().unwrap_or("value"), cmp_sym(*operator), value
+        ),
+        LogicOp::DropNulls { list_path, field } => {
+            format!("items of {} where {} is not null", trim(list_path), field.as_deref().unwrap_or("value"))
+        }
+        LogicOp::FilterRange { list_path, field, min, max, inclusive } => format!(
+            "items of {} where {} {} {} {} {}",
+            trim(list_path),
+            min,
+            if *inclusive { "<=" } else { "<" },
+            field.as_deref().unwrap_or("value"),
+            if *inclusive { "<=" } else { "<" },
+            max
+        ),
+        LogicOp::GroupBy { list_path, key_field, aggregate_field, aggregation } => format!(
+            "{} of {} grouped by {}",
+            agg_name(*aggregation),
+            list_ref(list_path, aggregate_field.as_deref()),
+            key_field
+        ),
+        LogicOp::Pivot { list_path, row_field, col_field, value_field, aggregation } => format!(
+            "{} of {} pivoted by {} (rows) and {} (columns)",
+            agg_name(*aggregation),
+            list_ref(list_path, value_field.as_deref()),
+            row_field,
+            col_field
+        ),
+        LogicOp::IndexBy { list_path, key_field } => format!("{} keyed by {}", trim(list_path), key_field),
+        LogicOp::And { conditions } => join_conditions(conditions, " AND "),
+        LogicOp::Or { conditions } => join_conditions(conditions, " OR "),
+        LogicOp::Equals { a, b } => format!("{} == {}", trim(a), trim(b)),
+        LogicOp::Contains { haystack, needle } => format!("{} contains {}", trim(haystack), trim(needle)),
+        LogicOp::JsonDiff { a, b } => format!("DIFF of {} against {}", trim(a), trim(b)),
+        LogicOp::Not { condition } => format!("NOT ({})", describe(condition)),
+        LogicOp::FilterString { list_path, field, operator, value, ignore_case } => format!(
+            "items of {} where {} {} {:?}{}",
+            trim(list_path),
+            field.as_deref().unwrap_or("value"),
+            str_op_name(*operator),
+            value,
+            if *ignore_case { " (ignoring case)" } else { "" }
+        ),
+        LogicOp::Sort { list_path, field, descending, then_by } => format!(
+            "{} sorted by {} {}{}",
+            trim(list_path),
+            field,
+            if *descending { "descending" } else { "ascending" },
+            then_by.as_ref().map(|t| format!(", then by {t}")).unwrap_or_default()
+        ),
+        LogicOp::Rank { list_path, field, output_field, descending } => format!(
+            "for each item in {}: {} = rank by {} {}",
+            trim(list_path), output_field, field, if *descending { "descending" } else { "ascending" }
+        ),
+        LogicOp::Take { list_path, count } => format!("first {} items of {}", count, trim(list_path)),
+        LogicOp::Slice { list_path, start, end } => format!(
+            "items {}..{} of {}",
+            start,
+            end.map(|e| e.to_string()).unwrap_or_default(),
+            trim(list_path)
+        ),
+        LogicOp::Reverse { list_path } => format!("{} reversed", trim(list_path)),
+        LogicOp::Flatten { path, depth } => match depth {
+            Some(d) => format!("{} flattened {} levels", trim(path), d),
+            None => format!("{} flattened", trim(path)),
+        },
+        LogicOp::MergeArrays { paths } => {
+            let paths: Vec<&str> = paths.iter().map(|p| trim(p)).collect();
+            format!("concat({})", paths.join(", "))
+        }
+        LogicOp::Chunk { list_path, size } => format!("{} in chunks of {}", trim(list_path), size),
+        LogicOp::Zip { left_path, right_path, left_key, right_key } => format!(
+            "zip({} as {}, {} as {})",
+            trim(left_path), left_key, trim(right_path), right_key
+        ),
+        LogicOp::Append { list_path, value } => format!("{} + [{}]", trim(list_path), constant(value)),
+        LogicOp::Nth { list_path, index, field } => {
+            let item = format!("{}[{}]", trim(list_path), index);
+            match field {
+                Some(f) => format!("{item}.{f}"),
+                None => item,
+            }
+        }
+        LogicOp::BuildObject { fields } => {
+            let parts: Vec<String> = fields.iter().map(|f| format!("{}: {}", f.key, f.path)).collect();
+            format!("{{ {} }}", parts.join(", "))
+        }
+        LogicOp::RenameKeys { path, mapping } => {
+            let renames: Vec<String> = mapping.iter().map(|(from, to)| format!("{from} -> {to}")).collect();
+            format!("{} with keys renamed ({})", trim(path), renames.join(", "))
+        }
+        LogicOp::TransformKeys { path, style, recursive } => format!(
+            "{} with {}keys in {}",
+            trim(path),
+            if *recursive { "all nested " } else { "" },
+            match style {
+                KeyStyle::Snake => "snake_case",
+                KeyStyle::Camel => "camelCase",
+                KeyStyle::Kebab => "kebab-case",
+            }
+        ),
+        LogicOp::MergeObjects { paths, deep } => {
+            let paths: Vec<&str> = paths.iter().map(|p| trim(p)).collect();
+            format!("{}merge({})", if *deep { "deep " } else { "" }, paths.join(", "))
+        }
+        LogicOp::ToUpper { path } => format!("upper({})", trim(path)),
+        LogicOp::ToLower { path } => format!("lower({})", trim(path)),
+        LogicOp::Trim { path } => format!("trim({})", trim(path)),
+        LogicOp::Substring { path, start, length: Some(len) } => {
+            format!("substring({}, from {}, {} chars)", trim(path), start, len)
+        }
+        LogicOp::Substring { path, start, length: None } => format!("substring({}, from {})", trim(path), start),
+        #[cfg(feature = "regex")]
+        LogicOp::RegexExtract { path, pattern, group } => format!("group {} of /{}/ in {}", group, pattern, trim(path)),
+        #[cfg(feature = "regex")]
+        LogicOp::RegexMatch { path, pattern } => format!("{} matches /{}/", trim(path), pattern),
+        #[cfg(feature = "regex")]
+        LogicOp::RegexReplace { path, pattern, replacement } => {
+            format!("{} with /{}/ replaced by {:?}", trim(path), pattern, replacement)
+        }
+        #[cfg(feature = "chrono")]
+        LogicOp::DateDiff { start_path, end_path, unit } => {
+            let unit = match unit {
+                super::dsl::DateUnit::Days => "days",
+                super::dsl::DateUnit::Hours => "hours",
+                super::dsl::DateUnit::Minutes => "minutes",
+            };
+            format!("{} between {} and {}", unit, trim(start_path), trim(end_path))
+        }
+        #[cfg(feature = "chrono")]
+        LogicOp::DateFormat { path, format } => format!("format_date({}, {:?})", trim(path), format),
+        LogicOp::FormatList { list_path, item_template, separator } => {
+            format!("join({:?} for each item in {}, {:?})", item_template, trim(list_path), separator)
+        }
+        LogicOp::FormatString { template, variables } => {
+            let args: Vec<String> = variables.iter().map(|v| format!("{}={}", v.key, v.path)).collect();
+            if args.is_empty() {
+                format!("format({template:?})")
+            } else {
+                format!("format({:?}, {})", template, args.join(", "))
+            }
+        }
+    }
+}
+
+fn trim(path: &str) -> &str {
+    path.strip_prefix('/').unwrap_or(path)
+}
+
+fn list_ref(list_path: &str, field: Option<&str>) -> String {
+    match field {
+        Some(f) => format!("{}[].{}", trim(list_path), f),
+        None => format!("{}[]", trim(list_path)),
+    }
+}
+
+fn constant(value: &ConstantValue) -> String {
+    value.to_value().to_string()
+}
+
+fn join_conditions(conditions: &[LogicOp], sep: &str) -> String {
+    let parts: Vec<String> = conditions.iter().map(|c| format!("({})", describe(c))).collect();
+    parts.join(sep)
+}
+
+fn math_sym(op: &MathOp) -> &'static str {
+    match op {
+        MathOp::Add => "+",
+        MathOp::Subtract => "-",
+        MathOp::Multiply => "*",
+        MathOp::Divide => "/",
+    }
+}
+
+fn zero_note(policy: Option<ZeroPolicy>) -> &'static str {
+    match policy {
+        None => "",
+        Some(ZeroPolicy::Error) => " (error if divisor is 0)",
+        Some(ZeroPolicy::Zero) => " (0 if divisor is 0)",
+        Some(ZeroPolicy::Null) => " (null if divisor is 0)",
+        Some(ZeroPolicy::Infinity) => " (\"Infinity\" if divisor is 0)",
+    }
+}
+
+fn cmp_sym(op: CmpOp) -> &'static str {
+    match op {
+        CmpOp::Gt => ">",
+        CmpOp::Lt => "<",
+        CmpOp::Eq => "==",
+        CmpOp::Gte => ">=",
+        CmpOp::Lte => "<=",
+    }
+}
+
+fn str_op_name(op: StrOp) -> &'static str {
+    match op {
+        StrOp::Eq => "equals",
+        StrOp::NotEq => "does not equal",
+        StrOp::Contains => "contains",
+        StrOp::StartsWith => "starts with",
+        StrOp::EndsWith => "ends with",
+    }
+}
+
+fn agg_name(kind: AggKind) -> &'static str {
+    match kind {
+        AggKind::Sum => "SUM",
+        AggKind::Count => "COUNT",
+        AggKind::Average => "AVERAGE",
+        AggKind::Min => "MIN",
+        AggKind::Max => "MAX",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::dsl::{AppDefinition, AppProgram};
+    use serde_json::json;
+
+    #[test]
+    fn explains_the_profitability_program() {
+        let program = AppProgram {
+            definition: AppDefinition {
+                name: "Project Profitability Analyzer".into(),
+                description: String::new(),
+                input_schema: json!({}),
+                output_schema: json!({}),
+            },
+            steps: serde_json::from_str(include_str!("../../fixtures/profit/Development.json")).unwrap(),
+            examples: Vec::new(),
+        };
+        let expected = "\
+# Project Profitability Analyzer
+temp/total_revenue = SUM of projects[].revenue
+temp/total_costs = SUM of projects[].costs
+temp/total_hours = SUM of projects[].hours_worked
+temp/total_overhead = temp/total_hours * overhead_rate
+temp/gross_profit = temp/total_revenue - temp/total_costs
+total_profit = temp/gross_profit - temp/total_overhead
+temp/projects_with_profit = for each item in projects: profit = revenue - costs
+temp/sorted_projects = temp/projects_with_profit sorted by profit descending
+most_profitable_project = temp/sorted_projects[0].name
+profit_margin = total_profit / temp/total_revenue * 100 (0 if denominator is 0)
+summary = format(\"Total profit {profit} at a {margin}% margin; best project: {top}.\", \
+profit=/total_profit, margin=/profit_margin, top=/most_profitable_project)";
+        assert_eq!(program.explain(), expected);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::error::{MetaError, RuntimeErrorKind};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Regexes compiled for one program run, so regex ops inside `MapItems`
+/// loops don't recompile per item. Cloning shares the compiled set, and it is
+/// dropped with the run's state.
+#[derive(Debug, Clone, Default)]
+pub struct Patterns(Arc<HashMap<String, Regex>>);
+
+impl Patterns {
+    /// Compile `pattern` into the set unless it is already there.
+    pub fn insert(&mut self, pattern: &str) -> Result<(), MetaError> {
+        if !self.0.contains_key(pattern) {
+            let re = compile(pattern)?;
+            Arc::make_mut(&mut self.0).insert(pattern.to_string(), re);
+        }
+        Ok(())
+    }
+
+    /// The compiled `pattern`, compiling it on the spot if it wasn't inserted.
+    pub fn get(&self, pattern: &str) -> Result<Regex, MetaError> {
+        match self.0.get(pattern) {
+            Some(re) => Ok(re.clone()),
+            None => compile(pattern),
+        }
+    }
+}
+
+pub fn compile(pattern: &str) -> Result<Regex, MetaError> {
+    Regex::new(pattern).map_err(|e| {
+        MetaError::runtime(RuntimeErrorKind::InvalidArgument, format!("Invalid regex '{pattern}': {e}"))
+    })
+}
@@ -0,0 +1,201 @@
+use super::dsl::{AppProgram, LogicOp};
+use super::runtime::{path_covers, paths_overlap};
+use serde_json::Value;
+
+impl AppProgram {
+    /// Copy of the program without steps whose output nothing uses: a step is
+    /// kept if it writes a declared `output_schema` property or something a
+    /// later kept step reads, so chains of dead steps are removed together.
+    /// Programs without declared output properties return their full state,
+    /// so every step is kept.
+    pub fn prune_dead_steps(&self) -> AppProgram {
+        if self.definition.output_schema.get("properties").and_then(|v| v.as_object()).is_none() {
+            return self.clone();
+        }
+        let outputs = output_paths(self);
+
+        let mut live = vec![false; self.steps.len()];
+        for i in (0..self.steps.len()).rev() {
+            let written = &self.steps[i].output_path;
+            live[i] = outputs.iter().any(|out| paths_overlap(written, out))
+                || self.steps[i + 1..].iter().zip(&live[i + 1..])
+                    .filter(|(_, kept)| **kept)
+                    .any(|(later, _)| later.operation.read_paths().iter().any(|r| paths_overlap(written, r)));
+        }
+
+        let mut pruned = self.clone();
+        pruned.steps = self.steps.iter().zip(live)
+            .filter_map(|(step, kept)| {
+                if !kept {
+                    log::debug!("   Pruning dead step [{}] ({})", step.id, step.output_path);
+                }
+                kept.then(|| step.clone())
+            })
+            .collect();
+        pruned
+    }
+
+    /// Copy of the program with repeated computations merged: a step whose
+    /// operation is identical to an earlier step's is dropped and later reads
+    /// of its output are pointed at the earlier step's output instead. Steps
+    /// are only merged when nothing in between writes to the operation's
+    /// inputs, nothing else writes to the kept or the dropped output, and the
+    /// dropped output isn't a declared output property. Programs without
+    /// declared output properties return their full state, so nothing is
+    /// merged.
+    pub fn dedupe_steps(&self) -> AppProgram {
+        if self.definition.output_schema.get("properties").and_then(|v| v.as_object()).is_none() {
+            return self.clone();
+        }
+        let outputs = output_paths(self);
+        let mut steps = self.steps.clone();
+        let mut j = 0;
+        while j < steps.len() {
+            let op = serde_json::to_value(&steps[j].operation).ok();
+            let reads: Vec<String> = steps[j].operation.read_paths().into_iter().map(String::from).collect();
+            let dup_path = steps[j].output_path.clone();
+
+            let survivor = (0..j).find(|&i| {
+                serde_json::to_value(&steps[i].operation).ok() == op
+                    && !steps[i + 1..j].iter().any(|k| reads.iter().any(|r| paths_overlap(&k.output_path, r)))
+                    && !steps.iter().enumerate()
+                        .any(|(k, s)| k != i && k != j && paths_overlap(&s.output_path, &steps[i].output_path))
+                    && !steps.iter().enumerate()
+                        .any(|(k, s)| k != j && paths_overlap(&s.output_path, &dup_path))
+                    && !outputs.iter().any(|out| paths_overlap(&dup_path, out))
+            });
+
+            let Some(i) = survivor else {
+                j += 1;
+                continue;
+            };
+            let kept_path = steps[i].output_path.clone();
+            log::debug!("   Merging step [{}] into [{}]", steps[j].id, steps[i].id);
+            steps.remove(j);
+            for later in &mut steps[j..] {
+                later.operation = rewrite_reads(&later.operation, &dup_path, &kept_path);
+            }
+        }
+
+        let mut deduped = self.clone();
+        deduped.steps = steps;
+        deduped
+    }
+}
+
+/// Pointer paths of the declared top-level output properties.
+fn output_paths(program: &AppProgram) -> Vec<String> {
+    program.definition.output_schema.get("properties")
+        .and_then(|v| v.as_object())
+        .map(|props| props.keys().map(|key| format!("/{}", key.replace('~', "~0").replace('/', "~1"))).collect())
+        .unwrap_or_default()
+}
+
+/// `op` with every read path at or below `from` moved under `to`. Only strings
+/// that are among the op's read paths are touched, so literals are left alone.
+fn rewrite_reads(op: &LogicOp, from: &str, to: &str) -> LogicOp {
+    let reads: Vec<&str> = op.read_paths().into_iter().filter(|r| path_covers(from, r)).collect();
+    if reads.is_empty() {
+        return op.clone();
+    }
+    let Ok(mut value) = serde_json::to_value(op) else { return op.clone() };
+    replace_strings(&mut value, &|s| reads.contains(&s).then(|| format!("{to}{}", &s[from.len()..])));
+    serde_json::from_value(value).unwrap_or_else(|_| op.clone())
+}
+
+fn replace_strings(value: &mut Value, replace: &dyn Fn(&str) -> Option<String>) {
+    match value {
+        Value::String(s) => {
+            if let Some(new) = replace(s) {
+                *s = new;
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| replace_strings(v, replace)),
+        Value::Object(map) => map.values_mut().for_each(|v| replace_strings(v, replace)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::dsl::{AppDefinition, AppProgram};
+    use serde_json::{json, Value};
+
+    fn program(output_schema: Value, steps: Value) -> AppProgram {
+        AppProgram {
+            definition: AppDefinition { name: "Test".into(), description: String::new(), input_schema: json!({}), output_schema },
+            steps: serde_json::from_value(steps).unwrap(),
+            examples: Vec::new(),
+        }
+    }
+
+    fn ids(program: &AppProgram) -> Vec<&str> {
+        program.steps.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[test]
+    fn prune_removes_dead_chains() {
+        let program = program(
+            json!({ "properties": { "profit": {} } }),
+            json!([
+                { "id": "gross", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" }, "output_path": "/temp/gross" },
+                { "id": "unused_total", "description": "", "operation": { "op": "sum", "list_path": "/xs" }, "output_path": "/temp/total" },
+                { "id": "unused_scaled", "description": "", "operation": { "op": "multiply", "a": "/temp/total", "b": "/rate" }, "output_path": "/temp/scaled" },
+                { "id": "profit", "description": "", "operation": { "op": "get", "path": "/temp/gross" }, "output_path": "/profit" }
+            ]),
+        );
+        assert_eq!(ids(&program.prune_dead_steps()), ["gross", "profit"]);
+    }
+
+    #[test]
+    fn prune_keeps_everything_without_declared_outputs() {
+        let program = program(
+            json!({ "type": "object" }),
+            json!([{ "id": "a", "description": "", "operation": { "op": "get", "path": "/x" }, "output_path": "/temp/a" }]),
+        );
+        assert_eq!(ids(&program.prune_dead_steps()), ["a"]);
+    }
+
+    fn sum_revenue(id: &str, output_path: &str) -> Value {
+        json!({ "id": id, "description": "", "operation": { "op": "sum", "list_path": "/projects", "field": "revenue" }, "output_path": output_path })
+    }
+
+    #[test]
+    fn dedupe_merges_identical_sums_and_rewires_reads() {
+        let program = program(
+            json!({ "properties": { "total": {}, "margin": {} } }),
+            json!([
+                sum_revenue("total", "/total"),
+                sum_revenue("revenue_again", "/temp/revenue"),
+                { "id": "margin", "description": "", "operation": { "op": "ratio", "numerator": "/profit", "denominator": "/temp/revenue" }, "output_path": "/margin" }
+            ]),
+        );
+        let deduped = program.dedupe_steps();
+        assert_eq!(ids(&deduped), ["total", "margin"]);
+        let margin = serde_json::to_value(&deduped.steps[1].operation).unwrap();
+        assert_eq!(margin["denominator"], "/total");
+        assert_eq!(margin["numerator"], "/profit");
+    }
+
+    #[test]
+    fn dedupe_keeps_steps_when_merging_would_be_unsafe() {
+        let outputs = json!({ "properties": { "a": {}, "b": {} } });
+        let overwrite_input = json!({ "id": "reload", "description": "", "operation": { "op": "get", "path": "/other" }, "output_path": "/projects" });
+        let overwrite_kept = json!({ "id": "reset", "description": "", "operation": { "op": "constant", "value": 0 }, "output_path": "/temp/x" });
+        let cases = [
+            // The input list changes between the two sums.
+            json!([sum_revenue("a", "/a"), overwrite_input, sum_revenue("b", "/b")]),
+            // The duplicate is itself a declared output.
+            json!([sum_revenue("a", "/temp/x"), sum_revenue("b", "/b")]),
+            // A later step writes over the survivor's output.
+            json!([sum_revenue("x", "/temp/x"), sum_revenue("y", "/temp/y"), overwrite_kept]),
+        ];
+        for steps in cases {
+            let program = program(outputs.clone(), steps);
+            assert_eq!(ids(&program.dedupe_steps()), ids(&program));
+        }
+
+        let no_outputs = program(json!({}), json!([sum_revenue("a", "/a"), sum_revenue("b", "/b")]));
+        assert_eq!(ids(&no_outputs.dedupe_steps()), ["a", "b"]);
+    }
+}
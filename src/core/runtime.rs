@@ -1,10 +1,27 @@
-use super::dsl::{CmpOp, LogicOp, AppProgram, ConstantValue, MathOp};
-use crate::error::MetaError;
+use super::dsl::{AggKind, CastType, CmpOp, KeyStyle, LogicOp, LogicStep, AppProgram, MathOp, NumberFormat, StrOp, ZeroPolicy};
+#[cfg(feature = "chrono")]
+use super::dates;
+#[cfg(feature = "regex")]
+use super::patterns;
+use crate::ai::schema_utils::{matches_type, type_name, validate_value};
+use crate::error::{MetaError, RuntimeErrorKind};
+use crate::events::{Listener, NoopListener};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Map, Value};
+use std::cmp::Ordering;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 pub struct RuntimeState {
     pub data: Value,
+    /// The element `MapItems` is currently processing, read as `/item`,
+    /// `/item/<field>` or a bare field name. Kept out of `data` so it never
+    /// collides with a top-level `item` key written by a step.
+    item: Option<Value>,
+    /// Regexes compiled up front for this run; shared by nested scopes.
+    #[cfg(feature = "regex")]
+    patterns: patterns::Patterns,
 }
 
 impl RuntimeState {
@@ -15,10 +32,27 @@ impl RuntimeState {
                 "inputs": inputs,
                 "temp": {}
             }),
+            item: None,
+            #[cfg(feature = "regex")]
+            patterns: patterns::Patterns::default(),
         }
     }
 
     pub fn get(&self, path: &str) -> Result<Value, MetaError> {
+        // 0. Inside `MapItems`, `/item...` and bare names resolve against the current item
+        if let Some(item) = &self.item {
+            if let Some(rest) = path.strip_prefix("/item").filter(|rest| rest.is_empty() || rest.starts_with('/')) {
+                return item.pointer(rest).cloned().ok_or_else(|| {
+                    MetaError::runtime_at(RuntimeErrorKind::PathNotFound, path, format!("Pointer not found in the current item: '{path}'"))
+                });
+            }
+            if !path.is_empty() && !path.starts_with('/')
+                && let Some(val) = field_value(item, path)
+            {
+                return Ok(val.clone());
+            }
+        }
+
         // 1. Try exact match
         if let Some(val) = self.data.pointer(path) {
             return Ok(val.clone());
@@ -46,7 +80,31 @@ impl RuntimeState {
             format!(" Available root keys: {:?}", available_roots)
         };
 
-        Err(MetaError::RuntimeError(format!("Pointer not found: '{}'.{}", path, hint)))
+        Err(MetaError::runtime_at(RuntimeErrorKind::PathNotFound, path, format!("Pointer not found: '{}'.{}", path, hint)))
+    }
+
+    /// Resolve a path with one `*` standing for every element of an array,
+    /// e.g. `/projects/*/revenue`. Elements lacking the remainder are skipped;
+    /// a path without `*` yields its single value.
+    pub fn collect(&self, path: &str) -> Result<Vec<Value>, MetaError> {
+        let Some((prefix, rest)) = split_wildcard(path) else {
+            return Ok(vec![self.get(path)?]);
+        };
+        if rest.contains("/*") {
+            return Err(MetaError::runtime_at(
+                RuntimeErrorKind::InvalidArgument,
+                path,
+                format!("Only one '*' wildcard is supported: {path}"),
+            ));
+        }
+        let list = self.get(prefix)?;
+        let items = list.as_array().ok_or_else(|| {
+            MetaError::runtime_at(RuntimeErrorKind::TypeMismatch, prefix, format!("Value at {prefix} is not an array"))
+        })?;
+        Ok(items.iter()
+            .filter_map(|item| if rest.is_empty() { Some(item) } else { item.pointer(rest) })
+            .cloned()
+            .collect())
     }
 
     pub fn set(&mut self, path: &str, value: Value) -> Result<(), MetaError> {
@@ -64,87 +122,404 @@ impl RuntimeState {
                 }
             }
             
-            // Handle /section/key (Standard)
-            if parts.len() == 3 {
-                let section = parts[1];
-                let key = parts[2];
-                
-                if let Some(root) = self.data.as_object_mut() {
-                    if !root.contains_key(section) {
-                        root.insert(section.to_string(), json!({}));
-                    }
-                    if let Some(section_obj) = root.get_mut(section).and_then(|v| v.as_object_mut()) {
-                        section_obj.insert(key.to_string(), value);
-                        return Ok(());
-                    }
+            // Handle /section/key (Standard) and deeper paths such as
+            // /report/summary/total, creating missing parent objects.
+            if parts.len() >= 3 && parts[1..].iter().all(|p| !p.is_empty()) {
+                let (key, sections) = parts[1..].split_last().expect("at least two segments");
+                let mut current = self.data.as_object_mut();
+                for section in sections {
+                    current = current.and_then(|obj| {
+                        obj.entry(section.to_string()).or_insert_with(|| json!({})).as_object_mut()
+                    });
+                }
+                if let Some(parent) = current {
+                    parent.insert(key.to_string(), value);
+                    return Ok(());
                 }
             }
-            return Err(MetaError::RuntimeError(format!("Cannot set path (invalid structure): {path}")));
+            return Err(MetaError::runtime_at(
+                RuntimeErrorKind::InvalidPath,
+                path,
+                format!("Cannot set path (invalid structure): {path}"),
+            ));
         }
         Ok(())
     }
 }
 
+/// The value a single step wrote, as recorded by `Runtime::execute_traced`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepTrace {
+    pub id: String,
+    pub output_path: String,
+    pub value: Value,
+    pub duration: Duration,
+}
+
+/// Wall-clock timings from `Runtime::execute_timed`. `total` also covers
+/// output extraction, so it is slightly above the sum of `per_step`.
+#[derive(Debug, Clone)]
+pub struct ExecMetrics {
+    pub total: Duration,
+    /// `(step id, duration)` in execution order.
+    pub per_step: Vec<(String, Duration)>,
+}
+
+/// A step skipped by `Runtime::execute_partial` because it failed.
+#[derive(Debug, Clone)]
+pub struct StepError {
+    pub id: String,
+    pub output_path: String,
+    pub error: String,
+}
+
+/// A property declared in `output_schema` that is absent from the output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputWarning {
+    /// Property path relative to the output root, e.g. `profit_margin` or `summary/total`.
+    pub property: String,
+    pub message: String,
+}
+
+/// How `Runtime::execute_with_strategy` builds the output from the final state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputStrategy {
+    /// Every property declared in `output_schema` must have been written;
+    /// a missing one is a `PathNotFound` error naming it.
+    SchemaStrict,
+    /// The declared properties that were written; missing ones are left out
+    /// (and logged), and nothing found yields `{}`.
+    SchemaWithPointerFallback,
+    /// As `SchemaWithPointerFallback`, but when no declared property is found
+    /// (or the schema declares none) the whole state is returned, inputs and
+    /// `/temp` values included. What `execute` does.
+    #[default]
+    FullState,
+}
+
 pub struct Runtime;
 
 impl Runtime {
     pub fn execute(program: &AppProgram, inputs: Value) -> Result<Value, MetaError> {
+        Self::execute_traced(program, inputs).map(|(output, _)| output)
+    }
+
+    /// Run `program` over each input (e.g. rows from `csv_input::parse_csv`),
+    /// returning one result per input in the same order. A failing input does
+    /// not stop the others.
+    pub fn execute_batch(program: &AppProgram, inputs: Vec<Value>) -> Vec<Result<Value, MetaError>> {
+        inputs.into_iter().map(|input| Self::execute(program, input)).collect()
+    }
+
+    /// Like `execute`, then coerces each output field to the scalar type its
+    /// `output_schema` property declares (e.g. `"12.5"` -> `12.5` for a number
+    /// field). Fails if a field can't be coerced.
+    pub fn execute_coerced(program: &AppProgram, inputs: Value) -> Result<Value, MetaError> {
+        let mut output = Self::execute(program, inputs)?;
+        coerce_output(program, &mut output)?;
+        Ok(output)
+    }
+
+    /// Like `execute`, extracting the output per `strategy`.
+    pub fn execute_with_strategy(program: &AppProgram, inputs: Value, strategy: OutputStrategy) -> Result<Value, MetaError> {
+        Self::run(program, inputs, &NoopListener, strategy).map(|(output, _)| output)
+    }
+
+    /// Like `execute`, reporting each step's written value to `listener`.
+    pub fn execute_with_listener(program: &AppProgram, inputs: Value, listener: &dyn Listener) -> Result<Value, MetaError> {
+        Self::run(program, inputs, listener, OutputStrategy::default()).map(|(output, _)| output)
+    }
+
+    /// Execute the program and also return the value written by each step.
+    pub fn execute_traced(program: &AppProgram, inputs: Value) -> Result<(Value, Vec<StepTrace>), MetaError> {
+        Self::run(program, inputs, &NoopListener, OutputStrategy::default())
+    }
+
+    /// `execute_traced`, writing the ordered traces to `path` as a JSON array.
+    pub fn execute_traced_to_file(program: &AppProgram, inputs: Value, path: impl AsRef<Path>) -> Result<Value, MetaError> {
+        let path = path.as_ref();
+        let (output, trace) = Self::execute_traced(program, inputs)?;
+        std::fs::write(path, serde_json::to_string_pretty(&trace)?).map_err(|e| {
+            MetaError::ConfigError(format!("Cannot write trace {}: {e}", path.display()))
+        })?;
+        Ok(output)
+    }
+
+    /// Execute the program and report how long each step (and the whole run) took.
+    pub fn execute_timed(program: &AppProgram, inputs: Value) -> Result<(Value, ExecMetrics), MetaError> {
+        let started = Instant::now();
+        let (output, trace) = Self::execute_traced(program, inputs)?;
+        let per_step = trace.into_iter().map(|t| (t.id, t.duration)).collect();
+        Ok((output, ExecMetrics { total: started.elapsed(), per_step }))
+    }
+
+    fn run(
+        program: &AppProgram,
+        inputs: Value,
+        listener: &dyn Listener,
+        strategy: OutputStrategy,
+    ) -> Result<(Value, Vec<StepTrace>), MetaError> {
         let mut state = RuntimeState::new(inputs);
+        let mut trace = Vec::with_capacity(program.steps.len());
         
         log::info!("🚀 Executing Program: {}", program.definition.name);
 
+        #[cfg(feature = "regex")]
+        for step in &program.steps {
+            precompile_patterns(&step.operation, &mut state.patterns).map_err(|e| with_step_context(e, step))?;
+        }
+
         for step in &program.steps {
             log::debug!("   Step [{}]: {}", step.id, step.description);
-            let result = Self::exec_op(&step.operation, &state)?;
-            state.set(&step.output_path, result)?;
+            let started = Instant::now();
+            let result = Self::exec_op(&step.operation, &state).map_err(|e| with_step_context(e, step))?;
+            state.set(&step.output_path, result.clone()).map_err(|e| with_step_context(e, step))?;
+            listener.on_step(step, &result);
+            trace.push(StepTrace {
+                id: step.id.clone(),
+                output_path: step.output_path.clone(),
+                value: result,
+                duration: started.elapsed(),
+            });
         }
 
-        // --- NEW OUTPUT EXTRACTION LOGIC ---
-        // Instead of returning state.data or looking for a magic "outputs" key,
-        // we explicitly construct the output based on the Output Schema.
-        if let Some(props) = program.definition.output_schema.get("properties").and_then(|v| v.as_object()) {
-            let mut structured_output = Map::new();
-            for key in props.keys() {
-                // 1. Look in root (e.g., "total_profit")
-                if let Some(val) = state.data.get(key) {
-                    structured_output.insert(key.clone(), val.clone());
-                } 
-                // 2. Look in pointer path (e.g., "/total_profit") just in case
-                else if let Some(val) = state.data.pointer(&format!("/{}", key)) {
-                    structured_output.insert(key.clone(), val.clone());
-                }
+        let output = extract_output(program, state, strategy);
+        let warnings = Self::output_warnings(program, &output);
+        if strategy == OutputStrategy::SchemaStrict && !warnings.is_empty() {
+            let missing: Vec<String> = warnings.into_iter().map(|w| w.message).collect();
+            return Err(MetaError::runtime(
+                RuntimeErrorKind::PathNotFound,
+                format!("Output is missing declared properties: {}", missing.join("; ")),
+            ));
+        }
+        for warning in warnings {
+            log::warn!("   {}", warning.message);
+        }
+        Ok((output, trace))
+    }
+
+    /// Like `execute`, but independent steps run concurrently. Steps are grouped
+    /// into waves by dataflow (a step waits for any earlier step whose output it
+    /// reads, or whose reads or output overlap its own output); each wave runs on
+    /// scoped threads against the same state and the results are written back in
+    /// program order, so the output is identical to `execute`. If several steps
+    /// of a wave fail, the first in program order is reported.
+    pub fn execute_parallel(program: &AppProgram, inputs: Value) -> Result<Value, MetaError> {
+        let mut state = RuntimeState::new(inputs);
+
+        log::info!("🚀 Executing Program (parallel): {}", program.definition.name);
+
+        #[cfg(feature = "regex")]
+        for step in &program.steps {
+            precompile_patterns(&step.operation, &mut state.patterns).map_err(|e| with_step_context(e, step))?;
+        }
+
+        for wave in step_waves(&program.steps) {
+            let results: Vec<Result<Value, MetaError>> = if let [only] = wave[..] {
+                vec![Self::exec_op(&program.steps[only].operation, &state)]
+            } else {
+                let state = &state;
+                std::thread::scope(|scope| {
+                    let handles: Vec<_> = wave.iter()
+                        .map(|&i| {
+                            let op = &program.steps[i].operation;
+                            scope.spawn(move || Self::exec_op(op, state))
+                        })
+                        .collect();
+                    handles.into_iter()
+                        .map(|h| h.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                        .collect()
+                })
+            };
+
+            for (&i, result) in wave.iter().zip(results) {
+                let step = &program.steps[i];
+                log::debug!("   Step [{}]: {}", step.id, step.description);
+                let value = result.map_err(|e| with_step_context(e, step))?;
+                state.set(&step.output_path, value).map_err(|e| with_step_context(e, step))?;
             }
-            
-            // If we found any matching data, return it.
-            if !structured_output.is_empty() {
-                return Ok(Value::Object(structured_output));
+        }
+
+        Ok(extract_output(program, state, OutputStrategy::default()))
+    }
+
+    /// Best-effort execution: a failing step is logged, recorded and skipped
+    /// (its output path stays unset) and the remaining steps still run. Returns
+    /// whatever output could be extracted alongside the skipped steps.
+    pub fn execute_partial(program: &AppProgram, inputs: Value) -> (Value, Vec<StepError>) {
+        let mut state = RuntimeState::new(inputs);
+        let mut errors = Vec::new();
+
+        log::info!("🚀 Executing Program (partial): {}", program.definition.name);
+
+        // A bad pattern only fails its own step, when that step runs.
+        #[cfg(feature = "regex")]
+        for step in &program.steps {
+            let _ = precompile_patterns(&step.operation, &mut state.patterns);
+        }
+
+        for step in &program.steps {
+            log::debug!("   Step [{}]: {}", step.id, step.description);
+            let result = Self::exec_op(&step.operation, &state)
+                .and_then(|value| state.set(&step.output_path, value));
+            if let Err(e) = result {
+                let e = with_step_context(e, step);
+                log::warn!("   Skipping step [{}]: {}", step.id, e);
+                errors.push(StepError {
+                    id: step.id.clone(),
+                    output_path: step.output_path.clone(),
+                    error: e.to_string(),
+                });
+            }
+        }
+
+        (extract_output(program, state, OutputStrategy::default()), errors)
+    }
+
+    /// Like `execute`, but checks `inputs` against the program's `input_schema`
+    /// before running and the constructed output against `output_schema`
+    /// afterwards (types and required properties).
+    pub fn execute_validated(program: &AppProgram, inputs: Value) -> Result<Value, MetaError> {
+        conform(&inputs, &program.definition.input_schema, "input")?;
+        let output = Self::execute(program, inputs)?;
+        conform(&output, &program.definition.output_schema, "output")?;
+        Ok(output)
+    }
+
+    /// Static dataflow check: step ids and output paths must be unique, and
+    /// every path an op reads must be an input or be written by an earlier step.
+    /// Returns one message per problem.
+    pub fn validate(program: &AppProgram) -> Result<(), Vec<String>> {
+        let input_keys: Option<Vec<&str>> = program.definition.input_schema
+            .get("properties")
+            .and_then(|v| v.as_object())
+            .map(|props| props.keys().map(String::as_str).collect());
+
+        let mut errors = Vec::new();
+        for (i, step) in program.steps.iter().enumerate() {
+            if let Some(first) = program.steps[..i].iter().find(|s| s.id == step.id) {
+                errors.push(format!("Duplicate step id '{}' (already used by the step writing '{}')", step.id, first.output_path));
+            }
+            if let Some(first) = program.steps[..i].iter().find(|s| s.output_path == step.output_path) {
+                errors.push(format!(
+                    "Step '{}' overwrites '{}', already written by step '{}'",
+                    step.id, step.output_path, first.id
+                ));
+            }
+        }
+
+        for (i, step) in program.steps.iter().enumerate() {
+            for path in step.operation.read_paths() {
+                // Reading a parent (e.g. all of `/temp`) is fine once anything under it is written.
+                let produced_earlier = program.steps[..i].iter()
+                    .any(|s| path_covers(&s.output_path, path) || path_covers(path, &s.output_path));
+                let produced_later = program.steps[i..].iter().find(|s| path_covers(&s.output_path, path));
+                if produced_earlier || is_input_path(path, input_keys.as_deref()) {
+                    continue;
+                }
+
+                match produced_later {
+                    Some(later) => errors.push(format!(
+                        "Step '{}' reads '{}' before it is written by step '{}'",
+                        step.id, path, later.id
+                    )),
+                    None => errors.push(format!(
+                        "Step '{}' reads '{}', which is neither an input nor produced by any step",
+                        step.id, path
+                    )),
+                }
             }
         }
 
-        // Fallback: If no schema properties matched (or schema is empty), return full state
-        Ok(state.data)
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// One warning per `output_schema` property missing from `output`, naming
+    /// any step that wrote a same-named value elsewhere (e.g. under `/temp`).
+    pub fn output_warnings(program: &AppProgram, output: &Value) -> Vec<OutputWarning> {
+        let mut warnings = Vec::new();
+        if let Some(props) = program.definition.output_schema.get("properties").and_then(|v| v.as_object()) {
+            missing_properties(program, output, props, "", &mut warnings);
+        }
+        warnings
     }
 
     fn exec_op(op: &LogicOp, state: &RuntimeState) -> Result<Value, MetaError> {
         match op {
-            LogicOp::Get { path } => state.get(path),
-            LogicOp::Constant { value } => {
-                Ok(match value {
-                    ConstantValue::String(s) => json!(s),
-                    ConstantValue::Number(n) => json!(n),
-                    ConstantValue::Bool(b) => json!(b),
-                    ConstantValue::Null => Value::Null,
+            LogicOp::Get { path, default } => match (state.get(path), default) {
+                (Err(_), Some(default)) => Ok(default.to_value()),
+                (result, _) => result,
+            },
+            LogicOp::Coalesce { paths, default } => {
+                Ok(paths.iter()
+                    .filter_map(|p| state.get(p).ok())
+                    .find(|v| !v.is_null())
+                    .unwrap_or_else(|| default.to_value()))
+            },
+            LogicOp::Constant { value } => Ok(value.to_value()),
+            LogicOp::ParseNumber { path } => {
+                let val = state.get(path)?;
+                coerce_f64(&val).map(|n| json!(n)).ok_or_else(|| {
+                    MetaError::runtime_at(RuntimeErrorKind::TypeMismatch, path, format!("Cannot parse value at {path} as a number: {val}"))
+                })
+            },
+            LogicOp::Cast { path, target } => {
+                let val = state.get(path)?;
+                cast(&val, *target).ok_or_else(|| {
+                    MetaError::runtime_at(RuntimeErrorKind::TypeMismatch, path, format!("Cannot cast value at {path} to {target:?}: {val}"))
                 })
             },
-            LogicOp::Add { a, b } => Ok(json!(get_f64(state, a)? + get_f64(state, b)?)),
-            LogicOp::Subtract { a, b } => Ok(json!(get_f64(state, a)? - get_f64(state, b)?)),
-            LogicOp::Multiply { a, b } => Ok(json!(get_f64(state, a)? * get_f64(state, b)?)),
-            LogicOp::Divide { a, b } => {
+            LogicOp::Lookup { path, table, default } => {
+                let val = state.get(path)?;
+                let key = lookup_key(&val).ok_or_else(|| {
+                    MetaError::runtime_at(RuntimeErrorKind::TypeMismatch, path, format!("Value at {path} cannot be used as a lookup key: {val}"))
+                })?;
+                match (table.get(&key), default) {
+                    (Some(hit), _) => Ok(hit.clone()),
+                    (None, Some(default)) => Ok(default.to_value()),
+                    (None, None) => Err(MetaError::runtime_at(
+                        RuntimeErrorKind::LookupMiss,
+                        path,
+                        format!("No lookup entry for key '{key}' (from {path})"),
+                    )),
+                }
+            },
+            LogicOp::Add { a, b } => Ok(json!(finite("add", get_f64(state, a)? + get_f64(state, b)?)?)),
+            LogicOp::Subtract { a, b } => Ok(json!(finite("subtract", get_f64(state, a)? - get_f64(state, b)?)?)),
+            LogicOp::Multiply { a, b } => Ok(json!(finite("multiply", get_f64(state, a)? * get_f64(state, b)?)?)),
+            LogicOp::Divide { a, b, on_zero } => {
+                divide(get_f64(state, a)?, get_f64(state, b)?, on_zero.unwrap_or(ZeroPolicy::Error))
+                    .map_err(|(kind, e)| MetaError::runtime_at(kind, b, e))
+            },
+            LogicOp::Percentage { numerator, denominator } => {
+                Ok(json!(finite("percentage", safe_ratio(get_f64(state, numerator)?, get_f64(state, denominator)?) * 100.0)?))
+            },
+            LogicOp::Ratio { numerator, denominator } => {
+                Ok(json!(finite("ratio", safe_ratio(get_f64(state, numerator)?, get_f64(state, denominator)?))?))
+            },
+            LogicOp::Modulo { a, b } => {
                 let v2 = get_f64(state, b)?;
-                if v2 == 0.0 { return Err(MetaError::RuntimeError("Division by zero".into())); }
-                Ok(json!(get_f64(state, a)? / v2))
+                if v2 == 0.0 { return Err(MetaError::runtime_at(RuntimeErrorKind::DivisionByZero, b, "Modulo by zero")); }
+                Ok(json!(get_f64(state, a)? % v2))
+            },
+            LogicOp::Power { base, exponent } => Ok(json!(finite("power", get_f64(state, base)?.powf(get_f64(state, exponent)?))?)),
+            LogicOp::Abs { path } => Ok(json!(get_f64(state, path)?.abs())),
+            // `0.0 - v` rather than `-v` so zero stays `0.0` instead of `-0.0`.
+            LogicOp::Negate { path } => Ok(json!(0.0 - get_f64(state, path)?)),
+            LogicOp::Sign { path } => {
+                let v = get_f64(state, path)?;
+                Ok(json!(if v > 0.0 { 1 } else if v < 0.0 { -1 } else { 0 }))
+            },
+            LogicOp::Clamp { path, min, max } => {
+                if min > max {
+                    return Err(MetaError::runtime(
+                        RuntimeErrorKind::InvalidArgument,
+                        format!("Clamp bounds are inverted: min {min} > max {max}"),
+                    ));
+                }
+                Ok(json!(get_f64(state, path)?.clamp(*min, *max)))
             },
-            LogicOp::Calculate { list_path, output_field, operator, a_field, b_field } => {
+            LogicOp::Calculate { list_path, output_field, operator, a_field, b_field, on_zero } => {
                 let mut arr = get_array(state, list_path)?;
                 let resolve_operand = |obj: &Map<String, Value>, target: &str| -> f64 {
                     if target.starts_with('/') {
@@ -157,33 +532,141 @@ impl Runtime {
                     if let Some(obj) = item.as_object_mut() {
                         let v1 = resolve_operand(obj, a_field);
                         let v2 = resolve_operand(obj, b_field);
+                        let op = format!("calculate '{output_field}'");
                         let res = match operator {
-                            MathOp::Add => v1 + v2,
-                            MathOp::Subtract => v1 - v2,
-                            MathOp::Multiply => v1 * v2,
-                            MathOp::Divide => if v2 != 0.0 { v1 / v2 } else { 0.0 },
+                            MathOp::Add => json!(finite(&op, v1 + v2)?),
+                            MathOp::Subtract => json!(finite(&op, v1 - v2)?),
+                            MathOp::Multiply => json!(finite(&op, v1 * v2)?),
+                            MathOp::Divide => divide(v1, v2, on_zero.unwrap_or(ZeroPolicy::Zero)).map_err(|(kind, e)| {
+                                MetaError::runtime_at(kind, list_path, format!("{e} computing '{output_field}'"))
+                            })?,
                         };
-                        obj.insert(output_field.clone(), json!(res));
+                        obj.insert(output_field.clone(), res);
+                    }
+                }
+                Ok(json!(arr))
+            },
+            LogicOp::RunningTotal { list_path, field, output_field } => {
+                let mut arr = get_array(state, list_path)?;
+                let mut total = 0.0;
+                for item in &mut arr {
+                    total += field_value(item, field).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                    if let Some(obj) = item.as_object_mut() {
+                        obj.insert(output_field.clone(), json!(total));
                     }
                 }
                 Ok(json!(arr))
             },
+            LogicOp::Normalize { list_path, field, output_field } => {
+                let mut arr = get_array(state, list_path)?;
+                let values = numeric_values(&arr, Some(field));
+                let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+                let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                for item in &mut arr {
+                    let scaled = match field_value(item, field).and_then(|v| v.as_f64()) {
+                        Some(x) if max > min => json!((x - min) / (max - min)),
+                        Some(_) => json!(0.0),
+                        None => Value::Null,
+                    };
+                    if let Some(obj) = item.as_object_mut() {
+                        obj.insert(output_field.clone(), scaled);
+                    }
+                }
+                Ok(json!(arr))
+            },
+            LogicOp::MapItems { list_path, steps } => {
+                let arr = get_array(state, list_path)?;
+                let mut scope = state.clone();
+                let mut result = Vec::with_capacity(arr.len());
+                for item in arr {
+                    scope.item = Some(item);
+                    for item_step in steps {
+                        let val = Self::exec_op(&item_step.operation, &scope)?;
+                        if let Some(obj) = scope.item.as_mut().and_then(|v| v.as_object_mut()) {
+                            obj.insert(item_step.output_field.clone(), val);
+                        }
+                    }
+                    result.push(scope.item.take().unwrap_or_default());
+                }
+                Ok(json!(result))
+            },
+            LogicOp::Chain { ops } => {
+                let mut scope = state.clone();
+                let mut last = None;
+                for (i, op) in ops.iter().enumerate() {
+                    let val = Self::exec_op(op, &scope)?;
+                    scope.set(&format!("/_{i}"), val.clone())?;
+                    last = Some(val);
+                }
+                last.ok_or_else(|| MetaError::runtime(RuntimeErrorKind::InvalidArgument, "Chain has no operations"))
+            },
             LogicOp::Sum { list_path, field } => {
                 let arr = get_array(state, list_path)?;
                 let sum: f64 = arr.iter().filter_map(|item| {
-                    if let Some(f) = field { item.get(f).and_then(|v| v.as_f64()) }
+                    if let Some(f) = field { field_value(item, f).and_then(|v| v.as_f64()) }
                     else { item.as_f64() }
                 }).sum();
                 Ok(json!(sum))
             },
+            LogicOp::SumFields { list_path, fields } => {
+                let mut totals = vec![0.0; fields.len()];
+                for item in &get_array(state, list_path)? {
+                    for (total, field) in totals.iter_mut().zip(fields) {
+                        *total += field_value(item, field).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                    }
+                }
+                Ok(Value::Object(fields.iter().cloned().zip(totals.into_iter().map(|t| json!(t))).collect()))
+            },
+            LogicOp::Product { list_path, field } => {
+                let arr = get_array(state, list_path)?;
+                let product: f64 = arr.iter().filter_map(|item| {
+                    if let Some(f) = field { field_value(item, f).and_then(|v| v.as_f64()) }
+                    else { item.as_f64() }
+                }).product();
+                Ok(json!(finite("product", product)?))
+            },
+            LogicOp::Reduce { list_path, field, operator, initial } => {
+                let arr = get_array(state, list_path)?;
+                let mut acc = *initial;
+                for v in numeric_values(&arr, field.as_deref()) {
+                    acc = match operator {
+                        MathOp::Add => acc + v,
+                        MathOp::Subtract => acc - v,
+                        MathOp::Multiply => acc * v,
+                        MathOp::Divide => {
+                            if v == 0.0 {
+                                let msg = "Division by zero in reduce";
+                                return Err(MetaError::runtime_at(RuntimeErrorKind::DivisionByZero, list_path, msg));
+                            }
+                            acc / v
+                        }
+                    };
+                }
+                Ok(json!(finite("reduce", acc)?))
+            },
             LogicOp::Count { list_path } => {
                 let arr = get_array(state, list_path)?;
                 Ok(json!(arr.len()))
             },
+            LogicOp::CountWhere { list_path, field, operator, value } => {
+                let arr = get_array(state, list_path)?;
+                let count = arr.iter()
+                    .filter(|item| numeric_match(item, field.as_deref(), *operator, *value))
+                    .count();
+                Ok(json!(count))
+            },
+            LogicOp::Distinct { list_path, field } => {
+                let arr = get_array(state, list_path)?;
+                Ok(json!(distinct_values(&arr, field.as_deref())))
+            },
+            LogicOp::DistinctCount { list_path, field } => {
+                let arr = get_array(state, list_path)?;
+                Ok(json!(distinct_values(&arr, field.as_deref()).len()))
+            },
             LogicOp::Min { list_path, field } => {
                 let arr = get_array(state, list_path)?;
                 let val = arr.iter().filter_map(|item| {
-                    if let Some(f) = field { item.get(f).and_then(|v| v.as_f64()) }
+                    if let Some(f) = field { field_value(item, f).and_then(|v| v.as_f64()) }
                     else { item.as_f64() }
                 }).fold(f64::INFINITY, f64::min);
                 Ok(json!(val))
@@ -191,52 +674,397 @@ impl Runtime {
             LogicOp::Max { list_path, field } => {
                 let arr = get_array(state, list_path)?;
                 let val = arr.iter().filter_map(|item| {
-                    if let Some(f) = field { item.get(f).and_then(|v| v.as_f64()) }
+                    if let Some(f) = field { field_value(item, f).and_then(|v| v.as_f64()) }
                     else { item.as_f64() }
                 }).fold(f64::NEG_INFINITY, f64::max);
                 Ok(json!(val))
             },
-            LogicOp::Pluck { path, key } => {
+            LogicOp::Median { list_path, field } => {
+                let mut values = numeric_values(&get_array(state, list_path)?, field.as_deref());
+                if values.is_empty() { return Ok(Value::Null); }
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                let mid = values.len() / 2;
+                let median = if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] };
+                Ok(json!(median))
+            },
+            LogicOp::StdDev { list_path, field, population } => {
+                let values = numeric_values(&get_array(state, list_path)?, field.as_deref());
+                let n = values.len() as f64;
+                let denom = if *population { n } else { n - 1.0 };
+                if denom <= 0.0 { return Ok(Value::Null); }
+                let mean = values.iter().sum::<f64>() / n;
+                let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / denom;
+                Ok(json!(variance.sqrt()))
+            },
+            LogicOp::Histogram { list_path, field, buckets } => {
+                let mut bounds = buckets.clone();
+                bounds.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                let mut counts = vec![0u64; bounds.len() + 1];
+                for v in numeric_values(&get_array(state, list_path)?, field.as_deref()) {
+                    counts[bounds.partition_point(|b| *b <= v)] += 1;
+                }
+                Ok(json!(counts))
+            },
+            LogicOp::WeightedAverage { list_path, value_field, weight_field } => {
+                let (weighted, total_weight) = get_array(state, list_path)?.iter()
+                    .filter_map(|item| {
+                        let value = field_value(item, value_field)?.as_f64()?;
+                        let weight = field_value(item, weight_field)?.as_f64()?;
+                        Some((value * weight, weight))
+                    })
+                    .fold((0.0, 0.0), |(sum, total), (vw, w)| (sum + vw, total + w));
+                if total_weight == 0.0 { return Ok(Value::Null); }
+                Ok(json!(weighted / total_weight))
+            },
+            LogicOp::MovingAverage { list_path, field, window } => {
+                if *window == 0 {
+                    return Err(MetaError::runtime(RuntimeErrorKind::InvalidArgument, "MovingAverage window must be at least 1"));
+                }
+                let values = numeric_values(&get_array(state, list_path)?, field.as_deref());
+                let averages: Vec<Value> = (0..values.len())
+                    .map(|i| if i + 1 < *window {
+                        Value::Null
+                    } else {
+                        json!(values[i + 1 - window..=i].iter().sum::<f64>() / *window as f64)
+                    })
+                    .collect();
+                Ok(json!(averages))
+            },
+            LogicOp::Collect { path } => Ok(json!(state.collect(path)?)),
+            LogicOp::Pluck { path, key, default } => {
                 let arr = get_array(state, path)?;
+                let fallback = default.as_ref().map_or(Value::Null, |d| d.to_value());
                 let plucked: Vec<Value> = arr.iter()
-                    .map(|obj| obj.get(key).cloned().unwrap_or(Value::Null))
+                    .map(|obj| field_value(obj, key).filter(|v| !v.is_null()).cloned().unwrap_or_else(|| fallback.clone()))
                     .collect();
                 Ok(json!(plucked))
             },
-            LogicOp::Sort { list_path, field, descending } => {
+            LogicOp::Sort { list_path, field, descending, then_by } => {
                 let mut arr = get_array(state, list_path)?;
                 arr.sort_by(|a, b| {
-                    let val_a = a.get(field).and_then(|v| v.as_f64()).unwrap_or(0.0);
-                    let val_b = b.get(field).and_then(|v| v.as_f64()).unwrap_or(0.0);
-                    val_a.partial_cmp(&val_b).unwrap_or(std::cmp::Ordering::Equal)
+                    let primary = compare_sort_keys(field_value(a, field), field_value(b, field), *descending);
+                    primary.then_with(|| match then_by {
+                        Some(f) => compare_sort_keys(field_value(a, f), field_value(b, f), false),
+                        None => Ordering::Equal,
+                    })
                 });
-                if *descending { arr.reverse(); }
                 Ok(json!(arr))
             },
+            LogicOp::Rank { list_path, field, output_field, descending } => {
+                let mut arr = get_array(state, list_path)?;
+                let cmp = |a: usize, b: usize| {
+                    compare_sort_keys(field_value(&arr[a], field), field_value(&arr[b], field), *descending)
+                };
+                let mut order: Vec<usize> = (0..arr.len()).collect();
+                order.sort_by(|&a, &b| cmp(a, b));
+
+                let mut ranks = vec![0; arr.len()];
+                for (pos, &idx) in order.iter().enumerate() {
+                    ranks[idx] = match pos {
+                        p if p > 0 && cmp(order[p - 1], idx) == Ordering::Equal => ranks[order[p - 1]],
+                        _ => pos + 1,
+                    };
+                }
+                for (item, rank) in arr.iter_mut().zip(ranks) {
+                    if let Some(obj) = item.as_object_mut() {
+                        obj.insert(output_field.clone(), json!(rank));
+                    }
+                }
+                Ok(json!(arr))
+            },
+            LogicOp::Take { list_path, count } => {
+                let mut arr = get_array(state, list_path)?;
+                arr.truncate(*count);
+                Ok(json!(arr))
+            },
+            LogicOp::Slice { list_path, start, end } => {
+                let arr = get_array(state, list_path)?;
+                let end = end.unwrap_or(arr.len()).min(arr.len());
+                let start = (*start).min(end);
+                Ok(json!(arr[start..end]))
+            },
+            LogicOp::Reverse { list_path } => {
+                let mut arr = get_array(state, list_path)?;
+                arr.reverse();
+                Ok(json!(arr))
+            },
+            LogicOp::Flatten { path, depth } => {
+                let arr = get_array(state, path)?;
+                Ok(json!(flatten(arr, depth.unwrap_or(1))))
+            },
+            LogicOp::MergeArrays { paths } => {
+                let mut merged = Vec::new();
+                for path in paths {
+                    merged.extend(get_array(state, path)?);
+                }
+                Ok(json!(merged))
+            },
+            LogicOp::Chunk { list_path, size } => {
+                if *size == 0 {
+                    return Err(MetaError::runtime(RuntimeErrorKind::InvalidArgument, "Chunk size must be at least 1"));
+                }
+                let arr = get_array(state, list_path)?;
+                let chunks: Vec<Value> = arr.chunks(*size).map(|c| json!(c)).collect();
+                Ok(json!(chunks))
+            },
+            LogicOp::Zip { left_path, right_path, left_key, right_key } => {
+                let left = get_array(state, left_path)?;
+                let right = get_array(state, right_path)?;
+                let pairs: Vec<Value> = left.into_iter().zip(right)
+                    .map(|(l, r)| json!({ left_key.as_str(): l, right_key.as_str(): r }))
+                    .collect();
+                Ok(json!(pairs))
+            },
+            LogicOp::Append { list_path, value } => {
+                let mut arr = get_array(state, list_path)?;
+                arr.push(value.to_value());
+                Ok(json!(arr))
+            },
+            LogicOp::Nth { list_path, index, field } => {
+                let arr = get_array(state, list_path)?;
+                let idx = if *index < 0 { arr.len() as i64 + index } else { *index };
+                let item = usize::try_from(idx).ok().and_then(|i| arr.get(i));
+                let val = match (item, field) {
+                    (Some(item), Some(f)) => field_value(item, f).cloned(),
+                    (item, None) => item.cloned(),
+                    (None, Some(_)) => None,
+                };
+                Ok(val.unwrap_or(Value::Null))
+            },
             LogicOp::FilterNumeric { list_path, field, operator, value } => {
+                let arr = get_array(state, list_path)?;
+                let filtered: Vec<Value> = arr.into_iter()
+                    .filter(|item| numeric_match(item, field.as_deref(), *operator, *value))
+                    .collect();
+                Ok(json!(filtered))
+            },
+            LogicOp::DropNulls { list_path, field } => {
+                let arr = get_array(state, list_path)?;
+                let kept: Vec<Value> = arr.into_iter()
+                    .filter(|item| match field {
+                        Some(f) => field_value(item, f).is_some_and(|v| !v.is_null()),
+                        None => !item.is_null(),
+                    })
+                    .collect();
+                Ok(json!(kept))
+            },
+            LogicOp::FilterRange { list_path, field, min, max, inclusive } => {
                 let arr = get_array(state, list_path)?;
                 let filtered: Vec<Value> = arr.into_iter().filter(|item| {
-                    let val = if let Some(f) = field { item.get(f).and_then(|v| v.as_f64()) }
+                    let val = if let Some(f) = field { field_value(item, f).and_then(|v| v.as_f64()) }
                               else { item.as_f64() };
+                    match val {
+                        Some(v) if *inclusive => v >= *min && v <= *max,
+                        Some(v) => v > *min && v < *max,
+                        None => false,
+                    }
+                }).collect();
+                Ok(json!(filtered))
+            },
+            LogicOp::FilterString { list_path, field, operator, value, ignore_case } => {
+                let arr = get_array(state, list_path)?;
+                let needle = if *ignore_case { value.to_lowercase() } else { value.clone() };
+                let filtered: Vec<Value> = arr.into_iter().filter(|item| {
+                    let val = if let Some(f) = field { field_value(item, f).and_then(|v| v.as_str()) }
+                              else { item.as_str() };
                     if let Some(v) = val {
+                        let hay = if *ignore_case { v.to_lowercase() } else { v.to_string() };
                         match operator {
-                            CmpOp::Gt => v > *value,
-                            CmpOp::Lt => v < *value,
-                            CmpOp::Eq => (v - *value).abs() < f64::EPSILON,
-                            CmpOp::Gte => v >= *value,
-                            CmpOp::Lte => v <= *value,
+                            StrOp::Eq => hay == needle,
+                            StrOp::NotEq => hay != needle,
+                            StrOp::Contains => hay.contains(&needle),
+                            StrOp::StartsWith => hay.starts_with(&needle),
+                            StrOp::EndsWith => hay.ends_with(&needle),
                         }
                     } else { false }
                 }).collect();
                 Ok(json!(filtered))
             },
+            LogicOp::GroupBy { list_path, key_field, aggregate_field, aggregation } => {
+                let arr = get_array(state, list_path)?;
+                let mut groups: Vec<(Value, Vec<f64>, usize)> = Vec::new();
+                for item in &arr {
+                    let key = field_value(item, key_field).cloned().unwrap_or(Value::Null);
+                    let num = aggregate_field.as_ref().and_then(|f| field_value(item, f)).and_then(|v| v.as_f64());
+                    let idx = match groups.iter().position(|(k, _, _)| *k == key) {
+                        Some(idx) => idx,
+                        None => {
+                            groups.push((key, Vec::new(), 0));
+                            groups.len() - 1
+                        }
+                    };
+                    let group = &mut groups[idx];
+                    group.1.extend(num);
+                    group.2 += 1;
+                }
+                let result: Vec<Value> = groups.into_iter().map(|(key, values, count)| {
+                    let mut row = Map::new();
+                    row.insert(key_field.clone(), key);
+                    row.insert("value".to_string(), aggregate(*aggregation, &values, count));
+                    Value::Object(row)
+                }).collect();
+                Ok(json!(result))
+            },
+            LogicOp::Pivot { list_path, row_field, col_field, value_field, aggregation } => {
+                let arr = get_array(state, list_path)?;
+                let mut columns: Vec<String> = Vec::new();
+                let mut rows: Vec<(Value, Map<String, Value>)> = Vec::new();
+                let mut cells: Vec<Vec<(Vec<f64>, usize)>> = Vec::new();
+                for item in &arr {
+                    let Some(col) = field_value(item, col_field).and_then(lookup_key) else { continue };
+                    let col_idx = columns.iter().position(|c| *c == col).unwrap_or_else(|| {
+                        columns.push(col);
+                        columns.len() - 1
+                    });
+                    let key = field_value(item, row_field).cloned().unwrap_or(Value::Null);
+                    let row_idx = rows.iter().position(|(k, _)| *k == key).unwrap_or_else(|| {
+                        rows.push((key, Map::new()));
+                        cells.push(Vec::new());
+                        rows.len() - 1
+                    });
+                    let row = &mut cells[row_idx];
+                    if row.len() <= col_idx {
+                        row.resize(col_idx + 1, (Vec::new(), 0));
+                    }
+                    row[col_idx].0.extend(value_field.as_ref().and_then(|f| field_value(item, f)).and_then(|v| v.as_f64()));
+                    row[col_idx].1 += 1;
+                }
+                let result: Vec<Value> = rows.into_iter().zip(cells).map(|((key, mut row), mut row_cells)| {
+                    row_cells.resize(columns.len(), (Vec::new(), 0));
+                    row.insert(row_field.clone(), key);
+                    for (col, (values, count)) in columns.iter().zip(row_cells) {
+                        row.insert(col.clone(), aggregate(*aggregation, &values, count));
+                    }
+                    Value::Object(row)
+                }).collect();
+                Ok(json!(result))
+            },
+            LogicOp::IndexBy { list_path, key_field } => {
+                let arr = get_array(state, list_path)?;
+                let mut index = Map::new();
+                for item in arr {
+                    if let Some(key) = field_value(&item, key_field).and_then(lookup_key) {
+                        index.insert(key, item);
+                    }
+                }
+                Ok(Value::Object(index))
+            },
+            LogicOp::And { conditions } => {
+                for cond in conditions {
+                    if !is_truthy(&Self::exec_op(cond, state)?) { return Ok(json!(false)); }
+                }
+                Ok(json!(true))
+            },
+            LogicOp::Or { conditions } => {
+                for cond in conditions {
+                    if is_truthy(&Self::exec_op(cond, state)?) { return Ok(json!(true)); }
+                }
+                Ok(json!(false))
+            },
+            LogicOp::Not { condition } => Ok(json!(!is_truthy(&Self::exec_op(condition, state)?))),
+            LogicOp::Equals { a, b } => Ok(json!(json_equal(&state.get(a)?, &state.get(b)?))),
+            LogicOp::Contains { haystack, needle } => {
+                let needle = state.get(needle)?;
+                match state.get(haystack)? {
+                    Value::Array(items) => Ok(json!(items.iter().any(|item| json_equal(item, &needle)))),
+                    Value::String(text) => Ok(json!(lookup_key(&needle).is_some_and(|n| text.contains(&n)))),
+                    other => Err(MetaError::runtime_at(
+                        RuntimeErrorKind::TypeMismatch,
+                        haystack,
+                        format!("Value at {haystack} is not an array or string ({})", type_name(&other)),
+                    )),
+                }
+            },
+            LogicOp::JsonDiff { a, b } => {
+                let mut diffs = Vec::new();
+                json_diff("", &state.get(a)?, &state.get(b)?, &mut diffs);
+                Ok(json!(diffs))
+            },
+            LogicOp::BuildObject { fields } => {
+                let obj: Map<String, Value> = fields.iter()
+                    .map(|f| (f.key.clone(), state.get(&f.path).unwrap_or(Value::Null)))
+                    .collect();
+                Ok(Value::Object(obj))
+            },
+            LogicOp::MergeObjects { paths, deep } => {
+                let mut merged = Map::new();
+                for path in paths {
+                    let Value::Object(obj) = state.get(path)? else {
+                        return Err(MetaError::runtime_at(RuntimeErrorKind::TypeMismatch, path, format!("Value at {path} is not an object")));
+                    };
+                    merge_into(&mut merged, obj, *deep);
+                }
+                Ok(Value::Object(merged))
+            },
+            LogicOp::RenameKeys { path, mapping } => match state.get(path)? {
+                Value::Object(obj) => {
+                    let (renamed, kept): (Vec<_>, Vec<_>) = obj.into_iter().partition(|(k, _)| mapping.contains_key(k));
+                    let mut result: Map<String, Value> = kept.into_iter().collect();
+                    for (key, val) in renamed {
+                        result.insert(mapping[&key].clone(), val);
+                    }
+                    Ok(Value::Object(result))
+                },
+                other => Ok(other),
+            },
+            LogicOp::TransformKeys { path, style, recursive } => Ok(transform_keys(state.get(path)?, *style, *recursive, true)),
+            LogicOp::ToUpper { path } => Ok(json!(get_str(state, path)?.to_uppercase())),
+            LogicOp::ToLower { path } => Ok(json!(get_str(state, path)?.to_lowercase())),
+            LogicOp::Trim { path } => Ok(json!(get_str(state, path)?.trim())),
+            LogicOp::Substring { path, start, length } => {
+                let s = get_str(state, path)?;
+                let chars = s.chars().skip(*start);
+                let sub: String = match length {
+                    Some(len) => chars.take(*len).collect(),
+                    None => chars.collect(),
+                };
+                Ok(json!(sub))
+            },
+            #[cfg(feature = "regex")]
+            LogicOp::RegexExtract { path, pattern, group } => {
+                let re = state.patterns.get(pattern)?;
+                let text = get_str(state, path)?;
+                let captured = re.captures(&text).and_then(|caps| caps.get(*group)).map(|m| m.as_str());
+                Ok(captured.map_or(Value::Null, |m| json!(m)))
+            },
+            #[cfg(feature = "regex")]
+            LogicOp::RegexMatch { path, pattern } => {
+                Ok(json!(state.patterns.get(pattern)?.is_match(&get_str(state, path)?)))
+            },
+            #[cfg(feature = "regex")]
+            LogicOp::RegexReplace { path, pattern, replacement } => {
+                let re = state.patterns.get(pattern)?;
+                Ok(json!(re.replace_all(&get_str(state, path)?, replacement.as_str())))
+            },
+            #[cfg(feature = "chrono")]
+            LogicOp::DateDiff { start_path, end_path, unit } => {
+                let start = get_date(state, start_path)?;
+                let end = get_date(state, end_path)?;
+                Ok(json!(dates::diff(start, end, *unit)))
+            },
+            #[cfg(feature = "chrono")]
+            LogicOp::DateFormat { path, format } => {
+                let date = get_date(state, path)?;
+                let text = dates::format(date, format)
+                    .map_err(|msg| MetaError::runtime(RuntimeErrorKind::InvalidArgument, msg))?;
+                Ok(json!(text))
+            },
+            LogicOp::FormatList { list_path, item_template, separator } => {
+                let rendered: Vec<String> = get_array(state, list_path)?.iter()
+                    .map(|item| render_item(item_template, item))
+                    .collect();
+                Ok(json!(rendered.join(separator)))
+            },
             LogicOp::FormatString { template, variables } => {
                 let mut result = template.clone();
                 for var in variables {
                     if let Ok(val) = state.get(&var.path) {
                         let s = match val {
                             Value::String(s) => s.clone(),
-                            Value::Number(n) => n.to_string(),
+                            Value::Number(n) => match (&var.format, n.as_f64()) {
+                                (Some(fmt), Some(f)) => format_number(f, fmt),
+                                _ => n.to_string(),
+                            },
                             Value::Bool(b) => b.to_string(),
                             _ => val.to_string(),
                         };
@@ -249,15 +1077,1902 @@ impl Runtime {
     }
 }
 
-fn get_f64(state: &RuntimeState, path: &str) -> Result<f64, MetaError> {
-    state.get(path)?
-        .as_f64()
-        .ok_or_else(|| MetaError::RuntimeError(format!("Value at {path} is not a number")))
+/// Fill `{field}` placeholders from `item`; an unclosed `{` is kept literally.
+fn render_item(template: &str, item: &Value) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}').map(|c| open + c) else { break };
+        out.push_str(&rest[..open]);
+        match field_value(item, &rest[open + 1..close]) {
+            Some(Value::String(s)) => out.push_str(s),
+            Some(Value::Null) | None => {}
+            Some(other) => out.push_str(&other.to_string()),
+        }
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    out
 }
 
-fn get_array(state: &RuntimeState, path: &str) -> Result<Vec<Value>, MetaError> {
-    state.get(path)?
-        .as_array()
-        .cloned()
-        .ok_or_else(|| MetaError::RuntimeError(format!("Value at {path} is not an array")))
-}
\ No newline at end of file
+/// Render a number per `NumberFormat`, e.g. `-1234.567` -> `-$1,234.57`.
+fn format_number(n: f64, fmt: &NumberFormat) -> String {
+    let digits = match fmt.decimals {
+        Some(d) => format!("{:.*}", d as usize, n.abs()),
+        None => n.abs().to_string(),
+    };
+    let (int_part, frac_part) = match digits.split_once('.') {
+        Some((i, f)) => (i.to_string(), format!(".{f}")),
+        None => (digits, String::new()),
+    };
+
+    let int_part = if fmt.thousands_separator {
+        let mut grouped = String::new();
+        for (i, c) in int_part.chars().enumerate() {
+            if i > 0 && (int_part.len() - i) % 3 == 0 {
+                grouped.push(',');
+            }
+            grouped.push(c);
+        }
+        grouped
+    } else {
+        int_part
+    };
+
+    let sign = if n < 0.0 && digits_nonzero(&int_part, &frac_part) { "-" } else { "" };
+    format!("{sign}{}{int_part}{frac_part}", fmt.prefix.as_deref().unwrap_or_default())
+}
+
+fn digits_nonzero(int_part: &str, frac_part: &str) -> bool {
+    int_part.chars().chain(frac_part.chars()).any(|c| c.is_ascii_digit() && c != '0')
+}
+
+/// Split a `Collect` path at its single `*` segment: `/a/*/b` becomes
+/// (`/a`, `/b`) and `/a/*` becomes (`/a`, ``). `None` when there is no
+/// wildcard segment (a `*` inside a key such as `/a*b` doesn't count).
+pub(crate) fn split_wildcard(path: &str) -> Option<(&str, &str)> {
+    let idx = path.find("/*").filter(|&i| matches!(path.as_bytes().get(i + 2), None | Some(b'/')))?;
+    Some((&path[..idx], &path[idx + 2..]))
+}
+
+/// Resolve a field within a list item. Accepts a plain key (`revenue`), a dotted
+/// path (`metrics.revenue`, `tags.0`) or a JSON pointer (`/metrics/revenue`).
+/// A plain key that literally exists on the item always wins over dotted lookup.
+fn field_value<'a>(item: &'a Value, field: &str) -> Option<&'a Value> {
+    if field.starts_with('/') {
+        return item.pointer(field);
+    }
+    if let Some(val) = item.get(field) {
+        return Some(val);
+    }
+    if !field.contains('.') {
+        return None;
+    }
+    field.split('.').try_fold(item, |node, part| match node {
+        Value::Object(map) => map.get(part),
+        Value::Array(arr) => part.parse::<usize>().ok().and_then(|i| arr.get(i)),
+        _ => None,
+    })
+}
+
+/// Numeric values of `field` (or the items themselves), skipping non-numbers.
+fn numeric_values(arr: &[Value], field: Option<&str>) -> Vec<f64> {
+    arr.iter().filter_map(|item| match field {
+        Some(f) => field_value(item, f).and_then(|v| v.as_f64()),
+        None => item.as_f64(),
+    }).collect()
+}
+
+/// Unique values by JSON equality, preserving first-seen order.
+fn distinct_values(arr: &[Value], field: Option<&str>) -> Vec<Value> {
+    let mut seen: Vec<Value> = Vec::new();
+    for item in arr {
+        let val = match field {
+            Some(f) => match field_value(item, f) { Some(v) => v, None => continue },
+            None => item,
+        };
+        if !seen.contains(val) {
+            seen.push(val.clone());
+        }
+    }
+    seen
+}
+
+/// Ordering used by `Sort`/`Rank`: strings compare lexicographically and sort
+/// after everything else; any other value compares numerically (non-numbers
+/// as 0). `descending` flips comparisons within a class only, so strings stay
+/// after numbers either way.
+fn compare_sort_keys(a: Option<&Value>, b: Option<&Value>, descending: bool) -> Ordering {
+    let within = |ord: Ordering| if descending { ord.reverse() } else { ord };
+    match (a.and_then(|v| v.as_str()), b.and_then(|v| v.as_str())) {
+        (Some(x), Some(y)) => within(x.cmp(y)),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => {
+            let x = a.and_then(|v| v.as_f64()).unwrap_or(0.0);
+            let y = b.and_then(|v| v.as_f64()).unwrap_or(0.0);
+            within(x.partial_cmp(&y).unwrap_or(Ordering::Equal))
+        }
+    }
+}
+
+/// Aggregate numeric `values` collected from `count` items. Average/Min/Max of
+/// no values yield `null`; Count reports items, not numeric values.
+fn aggregate(kind: AggKind, values: &[f64], count: usize) -> Value {
+    match kind {
+        AggKind::Sum => json!(values.iter().fold(0.0, |acc, v| acc + v)),
+        AggKind::Count => json!(count),
+        _ if values.is_empty() => Value::Null,
+        AggKind::Average => json!(values.iter().sum::<f64>() / values.len() as f64),
+        AggKind::Min => json!(values.iter().cloned().fold(f64::INFINITY, f64::min)),
+        AggKind::Max => json!(values.iter().cloned().fold(f64::NEG_INFINITY, f64::max)),
+    }
+}
+
+/// String form of a scalar used as a `Lookup`/`IndexBy` key. Whole numbers drop their
+/// fraction so `3.0` matches a `"3"` entry.
+fn lookup_key(val: &Value) -> Option<String> {
+    match val {
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Some(i.to_string()),
+            (None, Some(f)) if f.fract() == 0.0 && f.abs() < 1e15 => Some(format!("{}", f as i64)),
+            _ => Some(n.to_string()),
+        },
+        _ => None,
+    }
+}
+
+/// Numeric predicate shared by `FilterNumeric` and `CountWhere`. Items without
+/// a numeric value never match.
+fn numeric_match(item: &Value, field: Option<&str>, operator: CmpOp, value: f64) -> bool {
+    let val = match field {
+        Some(f) => field_value(item, f).and_then(|v| v.as_f64()),
+        None => item.as_f64(),
+    };
+    match val {
+        Some(v) => match operator {
+            CmpOp::Gt => v > value,
+            CmpOp::Lt => v < value,
+            CmpOp::Eq => (v - value).abs() < f64::EPSILON,
+            CmpOp::Gte => v >= value,
+            CmpOp::Lte => v <= value,
+        },
+        None => false,
+    }
+}
+
+fn flatten(arr: Vec<Value>, depth: usize) -> Vec<Value> {
+    if depth == 0 {
+        return arr;
+    }
+    arr.into_iter()
+        .flat_map(|v| match v {
+            Value::Array(inner) => flatten(inner, depth - 1),
+            other => vec![other],
+        })
+        .collect()
+}
+
+/// `a / b`, with a zero `b` handled per `policy`. Errs for `ZeroPolicy::Error`
+/// and for a quotient that overflows.
+fn divide(a: f64, b: f64, policy: ZeroPolicy) -> Result<Value, (RuntimeErrorKind, &'static str)> {
+    if b != 0.0 {
+        let quotient = a / b;
+        if !quotient.is_finite() {
+            return Err((RuntimeErrorKind::NonFinite, "Division overflowed to infinity"));
+        }
+        return Ok(json!(quotient));
+    }
+    match policy {
+        ZeroPolicy::Error => Err((RuntimeErrorKind::DivisionByZero, "Division by zero")),
+        ZeroPolicy::Zero => Ok(json!(0.0)),
+        ZeroPolicy::Null => Ok(Value::Null),
+        ZeroPolicy::Infinity if a > 0.0 => Ok(json!(INFINITY_SENTINELS[0])),
+        ZeroPolicy::Infinity if a < 0.0 => Ok(json!(INFINITY_SENTINELS[1])),
+        ZeroPolicy::Infinity => Ok(Value::Null),
+    }
+}
+
+/// The strings `ZeroPolicy::Infinity` writes in place of ±infinity, which
+/// JSON numbers can't hold.
+const INFINITY_SENTINELS: [&str; 2] = ["Infinity", "-Infinity"];
+
+fn is_infinity_sentinel(val: &Value) -> bool {
+    val.as_str().is_some_and(|s| INFINITY_SENTINELS.contains(&s))
+}
+
+/// `result` of arithmetic `op`, or a `NonFinite` error: serde_json would
+/// otherwise write NaN and infinities as a silent `null`.
+fn finite(op: &str, result: f64) -> Result<f64, MetaError> {
+    if result.is_finite() {
+        return Ok(result);
+    }
+    let what = if result.is_nan() { "NaN" } else { "an infinite value (overflow)" };
+    Err(MetaError::runtime(RuntimeErrorKind::NonFinite, format!("{op} produced {what}")))
+}
+
+/// Division for `Ratio`/`Percentage`: a zero denominator yields 0.0.
+fn safe_ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator == 0.0 { 0.0 } else { numerator / denominator }
+}
+
+/// Deep equality as used by `Equals`: numbers compare by value.
+fn json_equal(a: &Value, b: &Value) -> bool {
+    let mut diffs = Vec::new();
+    json_diff("", a, b, &mut diffs);
+    diffs.is_empty()
+}
+
+/// Append a `{path, a, b}` entry to `out` for every leaf where `a` and `b`
+/// differ. Numbers compare by value; objects and arrays are walked so the
+/// report points at the deepest differing key or index.
+fn json_diff(path: &str, a: &Value, b: &Value, out: &mut Vec<Value>) {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) if x.as_f64() == y.as_f64() => {},
+        (Value::Object(x), Value::Object(y)) => {
+            for (key, av) in x {
+                let child = format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+                match y.get(key) {
+                    Some(bv) => json_diff(&child, av, bv, out),
+                    None => out.push(json!({ "path": child, "a": av })),
+                }
+            }
+            for (key, bv) in y.iter().filter(|(k, _)| !x.contains_key(*k)) {
+                let child = format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+                out.push(json!({ "path": child, "b": bv }));
+            }
+        },
+        (Value::Array(x), Value::Array(y)) => {
+            for i in 0..x.len().max(y.len()) {
+                let child = format!("{path}/{i}");
+                match (x.get(i), y.get(i)) {
+                    (Some(av), Some(bv)) => json_diff(&child, av, bv, out),
+                    (Some(av), None) => out.push(json!({ "path": child, "a": av })),
+                    (None, Some(bv)) => out.push(json!({ "path": child, "b": bv })),
+                    (None, None) => {},
+                }
+            }
+        },
+        _ if a == b => {},
+        _ => out.push(json!({ "path": path, "a": a, "b": b })),
+    }
+}
+
+/// Boolean coercion used by `And`/`Or`/`Not`: `null`, `false`, `0`, `""`, `[]`
+/// and `{}` are false; everything else is true.
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+/// Read a number, also accepting numeric strings such as `"50.0"` or `" 12 "`.
+/// An infinity sentinel from `ZeroPolicy::Infinity` is a `NonFinite` error
+/// rather than a type mismatch, since arithmetic on it can't produce JSON.
+fn get_f64(state: &RuntimeState, path: &str) -> Result<f64, MetaError> {
+    let val = state.get(path)?;
+    if is_infinity_sentinel(&val) {
+        return Err(MetaError::runtime_at(
+            RuntimeErrorKind::NonFinite,
+            path,
+            format!("Value at {path} is {val} (a divide-by-zero result) and can't be used as a number"),
+        ));
+    }
+    coerce_f64(&val).ok_or_else(|| {
+        MetaError::runtime_at(RuntimeErrorKind::TypeMismatch, path, format!("Value at {path} is not a number"))
+    })
+}
+
+/// Numbers as-is; strings parsed after trimming. Non-finite parses and other
+/// types (bools, null) are rejected.
+fn coerce_f64(val: &Value) -> Option<f64> {
+    match val {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse::<f64>().ok().filter(|n| n.is_finite()),
+        _ => None,
+    }
+}
+
+fn get_str(state: &RuntimeState, path: &str) -> Result<String, MetaError> {
+    match state.get(path)? {
+        Value::String(s) => Ok(s),
+        _ => Err(MetaError::runtime_at(RuntimeErrorKind::TypeMismatch, path, format!("Value at {path} is not a string"))),
+    }
+}
+
+/// Compile every regex in `op` (including nested ops) into `compiled`, so a
+/// bad pattern fails before any step runs and each pattern compiles once per run.
+#[cfg(feature = "regex")]
+fn precompile_patterns(op: &LogicOp, compiled: &mut patterns::Patterns) -> Result<(), MetaError> {
+    match op {
+        LogicOp::RegexExtract { pattern, .. }
+        | LogicOp::RegexMatch { pattern, .. }
+        | LogicOp::RegexReplace { pattern, .. } => compiled.insert(pattern),
+        LogicOp::Chain { ops: nested }
+        | LogicOp::And { conditions: nested }
+        | LogicOp::Or { conditions: nested } => nested.iter().try_for_each(|op| precompile_patterns(op, compiled)),
+        LogicOp::Not { condition } => precompile_patterns(condition, compiled),
+        LogicOp::MapItems { steps, .. } => steps.iter().try_for_each(|s| precompile_patterns(&s.operation, compiled)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(feature = "chrono")]
+fn get_date(state: &RuntimeState, path: &str) -> Result<chrono::DateTime<chrono::FixedOffset>, MetaError> {
+    let text = get_str(state, path)?;
+    dates::parse_iso(&text).ok_or_else(|| {
+        MetaError::runtime_at(RuntimeErrorKind::TypeMismatch, path, format!("Value at {path} is not an ISO-8601 date: '{text}'"))
+    })
+}
+
+/// Rewrite object keys in `style`. Nested objects (and objects inside arrays)
+/// are only visited when `recursive`; `top` marks the value at the op's path.
+fn transform_keys(value: Value, style: KeyStyle, recursive: bool, top: bool) -> Value {
+    match value {
+        Value::Object(obj) if top || recursive => Value::Object(
+            obj.into_iter()
+                .map(|(k, v)| (convert_key(&k, style), transform_keys(v, style, recursive, false)))
+                .collect(),
+        ),
+        Value::Array(items) if recursive => {
+            Value::Array(items.into_iter().map(|v| transform_keys(v, style, recursive, false)).collect())
+        },
+        other => other,
+    }
+}
+
+/// Split `key` into lowercase words at `_`, `-`, spaces and case changes
+/// (`HTTPServer` -> `http`, `server`) and rejoin them in `style`. Keys with
+/// no word characters are returned unchanged.
+fn convert_key(key: &str, style: KeyStyle) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    let mut words: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if matches!(c, '_' | '-' | ' ') {
+            words.extend((!current.is_empty()).then(|| std::mem::take(&mut current)));
+            continue;
+        }
+        if c.is_uppercase() && !current.is_empty() {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+        current.extend(c.to_lowercase());
+    }
+    words.extend((!current.is_empty()).then_some(current));
+    if words.is_empty() {
+        return key.to_string();
+    }
+    match style {
+        KeyStyle::Snake => words.join("_"),
+        KeyStyle::Kebab => words.join("-"),
+        KeyStyle::Camel => words.iter().enumerate().map(|(i, w)| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) if i > 0 => first.to_uppercase().chain(chars).collect(),
+                _ => w.clone(),
+            }
+        }).collect(),
+    }
+}
+
+/// Copy `source`'s keys into `target`. When `deep`, objects present on both
+/// sides are merged key by key instead of replaced.
+fn merge_into(target: &mut Map<String, Value>, source: Map<String, Value>, deep: bool) {
+    for (key, value) in source {
+        match (target.get_mut(&key), value) {
+            (Some(Value::Object(existing)), Value::Object(incoming)) if deep => merge_into(existing, incoming, deep),
+            (_, value) => {
+                target.insert(key, value);
+            }
+        }
+    }
+}
+
+fn get_array(state: &RuntimeState, path: &str) -> Result<Vec<Value>, MetaError> {
+    state.get(path)?
+        .as_array()
+        .cloned()
+        .ok_or_else(|| MetaError::runtime_at(RuntimeErrorKind::TypeMismatch, path, format!("Value at {path} is not an array")))
+}
+
+fn extract_output(program: &AppProgram, state: RuntimeState, strategy: OutputStrategy) -> Value {
+    // --- NEW OUTPUT EXTRACTION LOGIC ---
+    // Instead of returning state.data or looking for a magic "outputs" key,
+    // we explicitly construct the output based on the Output Schema.
+    let structured_output = program.definition.output_schema.get("properties")
+        .and_then(|v| v.as_object())
+        .map(|props| collect_properties(&state.data, props, ""))
+        .unwrap_or_default();
+
+    // Fallback: If no schema properties matched (or schema is empty), return full state
+    if structured_output.is_empty() && strategy == OutputStrategy::FullState {
+        return state.data;
+    }
+    Value::Object(structured_output)
+}
+
+/// Build the object described by `props` from the state. A value written at a
+/// property's own path (e.g. `/summary`) is used as-is; otherwise nested
+/// object schemas are assembled from their leaf paths (`/summary/total`, ...).
+fn collect_properties(data: &Value, props: &Map<String, Value>, prefix: &str) -> Map<String, Value> {
+    let mut collected = Map::new();
+    for (key, prop_schema) in props {
+        let path = format!("{prefix}/{}", key.replace('~', "~0").replace('/', "~1"));
+        if let Some(val) = data.pointer(&path) {
+            collected.insert(key.clone(), val.clone());
+        } else if let Some(nested) = prop_schema.get("properties").and_then(|v| v.as_object()) {
+            let sub = collect_properties(data, nested, &path);
+            if !sub.is_empty() {
+                collected.insert(key.clone(), Value::Object(sub));
+            }
+        }
+    }
+    collected
+}
+
+fn missing_properties(
+    program: &AppProgram,
+    output: &Value,
+    props: &Map<String, Value>,
+    prefix: &str,
+    warnings: &mut Vec<OutputWarning>,
+) {
+    for (key, prop_schema) in props {
+        let path = format!("{prefix}/{}", key.replace('~', "~0").replace('/', "~1"));
+        if output.pointer(&path).is_some() {
+            if let Some(nested) = prop_schema.get("properties").and_then(|v| v.as_object()) {
+                missing_properties(program, output, nested, &path, warnings);
+            }
+            continue;
+        }
+
+        let property = path.trim_start_matches('/').to_string();
+        let misplaced = program.steps.iter()
+            .find(|s| s.output_path.rsplit('/').next() == path.rsplit('/').next());
+        let message = match misplaced {
+            Some(step) => format!(
+                "property `{property}` was never written by any step (step '{}' writes '{}'; the output needs '{path}')",
+                step.id, step.output_path
+            ),
+            None => format!("property `{property}` was never written by any step"),
+        };
+        warnings.push(OutputWarning { property, message });
+    }
+}
+
+/// Coerce each extracted output field to the scalar type its `output_schema`
+/// property declares (e.g. `"12.5"` -> `12.5` for a number field). Nulls,
+/// values that already match and infinity sentinels in number fields are left
+/// alone; an impossible coercion is a `TypeMismatch` error naming the field.
+fn coerce_output(program: &AppProgram, output: &mut Value) -> Result<(), MetaError> {
+    let Some(props) = program.definition.output_schema.get("properties").and_then(|v| v.as_object()) else {
+        return Ok(());
+    };
+    coerce_fields(output, props, "")
+}
+
+fn coerce_fields(value: &mut Value, props: &Map<String, Value>, prefix: &str) -> Result<(), MetaError> {
+    let Some(fields) = value.as_object_mut() else { return Ok(()) };
+
+    for (key, prop_schema) in props {
+        let Some(val) = fields.get_mut(key) else { continue };
+        let path = format!("{prefix}/{key}");
+        if let Some(nested) = prop_schema.get("properties").and_then(|v| v.as_object()) {
+            coerce_fields(val, nested, &path)?;
+        }
+        let types: Vec<&str> = match prop_schema.get("type") {
+            Some(Value::String(t)) => vec![t.as_str()],
+            Some(Value::Array(ts)) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => continue,
+        };
+        if val.is_null() || types.iter().any(|t| matches_type(val, t)) {
+            continue;
+        }
+        if is_infinity_sentinel(val) && types.iter().any(|t| matches!(*t, "number" | "integer")) {
+            continue;
+        }
+        match types.iter().find_map(|t| coerce_scalar(val, t)) {
+            Some(coerced) => *val = coerced,
+            None => {
+                return Err(MetaError::runtime_at(
+                    RuntimeErrorKind::TypeMismatch,
+                    &path,
+                    format!("Output '{path}' is a {} and cannot be coerced to {}", type_name(val), types.join(" or ")),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn coerce_scalar(val: &Value, target: &str) -> Option<Value> {
+    match (target, val) {
+        ("number", _) => coerce_f64(val).map(|n| json!(n)),
+        ("integer", _) => coerce_f64(val).filter(|n| n.fract() == 0.0).map(|n| json!(n as i64)),
+        ("boolean", Value::String(s)) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" => Some(json!(true)),
+            "false" => Some(json!(false)),
+            _ => None,
+        },
+        ("string", Value::Number(n)) => Some(json!(n.to_string())),
+        ("string", Value::Bool(b)) => Some(json!(b.to_string())),
+        _ => None,
+    }
+}
+
+/// `Cast` conversion rules (see the op's description); `None` if impossible.
+fn cast(val: &Value, target: CastType) -> Option<Value> {
+    match (target, val) {
+        (CastType::String, Value::String(_)) | (CastType::Number, Value::Number(_)) | (CastType::Bool, Value::Bool(_)) => {
+            Some(val.clone())
+        }
+        (CastType::String, Value::Null) => Some(json!("")),
+        (CastType::String, Value::Number(n)) if n.is_f64() => n.as_f64().map(|f| json!(f.to_string())),
+        (CastType::String, _) => Some(json!(val.to_string())),
+        (CastType::Number, Value::Bool(b)) => Some(json!(u8::from(*b))),
+        (CastType::Number, Value::String(_)) => coerce_f64(val).map(|n| json!(n)),
+        (CastType::Number, _) => None,
+        (CastType::Bool, Value::Null) => Some(json!(false)),
+        (CastType::Bool, Value::Number(n)) => n.as_f64().map(|f| json!(f != 0.0)),
+        (CastType::Bool, Value::String(s)) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => Some(json!(true)),
+            "false" | "0" | "no" | "" => Some(json!(false)),
+            _ => None,
+        },
+        (CastType::Bool, _) => None,
+    }
+}
+
+/// Attach the step that raised a runtime error, displayed as e.g.
+/// `Step 'calc_margin' (Compute profit margin): Division by zero`. A step
+/// already set is kept.
+fn with_step_context(err: MetaError, step: &LogicStep) -> MetaError {
+    match err {
+        MetaError::RuntimeError { kind, path, step_id: None, message, .. } => MetaError::RuntimeError {
+            kind,
+            path,
+            step_id: Some(step.id.clone()),
+            step_description: Some(step.description.clone()).filter(|d| !d.is_empty()),
+            message,
+        },
+        other => other,
+    }
+}
+
+/// Step indices grouped into waves that can run concurrently: each step lands
+/// one wave after the latest earlier step it conflicts with.
+fn step_waves(steps: &[LogicStep]) -> Vec<Vec<usize>> {
+    let reads: Vec<Vec<&str>> = steps.iter().map(|s| s.operation.read_paths()).collect();
+    let mut wave_of: Vec<usize> = Vec::with_capacity(steps.len());
+    let mut waves: Vec<Vec<usize>> = Vec::new();
+
+    for (i, step) in steps.iter().enumerate() {
+        let wave = (0..i)
+            .filter(|&j| {
+                let written = &steps[j].output_path;
+                paths_overlap(written, &step.output_path)
+                    || reads[i].iter().any(|r| paths_overlap(written, r))
+                    || reads[j].iter().any(|r| paths_overlap(&step.output_path, r))
+            })
+            .map(|j| wave_of[j] + 1)
+            .max()
+            .unwrap_or(0);
+        wave_of.push(wave);
+        if wave == waves.len() {
+            waves.push(Vec::new());
+        }
+        waves[wave].push(i);
+    }
+    waves
+}
+
+/// Conservative overlap test: either path contains the other, also comparing
+/// with any `/inputs` prefix dropped since `RuntimeState::get` falls back into it.
+pub(crate) fn paths_overlap(a: &str, b: &str) -> bool {
+    fn strip(p: &str) -> &str {
+        p.strip_prefix("/inputs").filter(|rest| rest.is_empty() || rest.starts_with('/')).unwrap_or(p)
+    }
+    let (a2, b2) = (strip(a), strip(b));
+    path_covers(a, b) || path_covers(b, a) || path_covers(a2, b2) || path_covers(b2, a2)
+}
+
+/// True if writing to `written` makes `read` resolvable (same path or a parent of it).
+pub(crate) fn path_covers(written: &str, read: &str) -> bool {
+    read == written || read.strip_prefix(written).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Mirrors `RuntimeState::get`'s lookup: `/inputs/<key>` or a bare `/<key>` that
+/// falls back into the inputs. With no declared input properties, anything goes
+/// except `/temp`, which only steps write.
+fn is_input_path(path: &str, input_keys: Option<&[&str]>) -> bool {
+    if path == "/temp" || path.starts_with("/temp/") {
+        return false;
+    }
+    if path == "/inputs" {
+        return true;
+    }
+    let Some(keys) = input_keys else { return true };
+    let rel = path.strip_prefix("/inputs").unwrap_or(path);
+    let root = rel.trim_start_matches('/').split('/').next().unwrap_or_default();
+    keys.contains(&root)
+}
+
+/// `ValidationFailed("Invalid <label>: ...")` listing every violation reported
+/// by `schema_utils::validate_value`, each as `<pointer>: <problem>`.
+fn conform(value: &Value, schema: &Value, label: &str) -> Result<(), MetaError> {
+    validate_value(value, schema).map_err(|violations| {
+        let violations: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        MetaError::ValidationFailed(format!("Invalid {label}: {}", violations.join("; ")))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dsl::AppDefinition;
+
+    fn program(input_schema: Value, output_schema: Value, steps: Value) -> AppProgram {
+        AppProgram {
+            definition: AppDefinition { name: "Test".into(), description: String::new(), input_schema, output_schema },
+            steps: serde_json::from_value(steps).unwrap(),
+            examples: Vec::new(),
+        }
+    }
+
+    /// `profit = revenue - costs`, with both inputs required numbers.
+    fn profit_program() -> AppProgram {
+        program(
+            json!({
+                "type": "object",
+                "properties": { "revenue": { "type": "number" }, "costs": { "type": "number" } },
+                "required": ["revenue", "costs"]
+            }),
+            json!({ "type": "object", "properties": { "profit": { "type": "number" } } }),
+            json!([{
+                "id": "calc_profit",
+                "description": "Revenue minus costs",
+                "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" },
+                "output_path": "/profit"
+            }]),
+        )
+    }
+
+    #[test]
+    fn execute_validated_accepts_conforming_input() {
+        let output = Runtime::execute_validated(&profit_program(), json!({ "revenue": 10, "costs": 3 })).unwrap();
+        assert_eq!(output, json!({ "profit": 7.0 }));
+    }
+
+    #[test]
+    fn execute_validated_rejects_missing_required_field() {
+        let err = Runtime::execute_validated(&profit_program(), json!({ "revenue": 10 })).unwrap_err();
+        assert!(matches!(&err, MetaError::ValidationFailed(msg) if msg.contains("costs")), "{err}");
+    }
+
+    #[test]
+    fn execute_validated_rejects_wrong_typed_field() {
+        let err = Runtime::execute_validated(&profit_program(), json!({ "revenue": "ten", "costs": 3 })).unwrap_err();
+        assert!(matches!(&err, MetaError::ValidationFailed(msg) if msg.contains("/revenue")), "{err}");
+    }
+
+    /// `profit_program`'s schemas around `steps`.
+    fn with_steps(steps: Value) -> AppProgram {
+        let base = profit_program();
+        program(base.definition.input_schema, base.definition.output_schema, steps)
+    }
+
+    #[test]
+    fn validate_accepts_well_ordered_program() {
+        let program = with_steps(json!([
+            { "id": "margin", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" }, "output_path": "/temp/margin" },
+            { "id": "profit", "description": "", "operation": { "op": "get", "path": "/temp/margin" }, "output_path": "/profit" }
+        ]));
+        assert_eq!(Runtime::validate(&program), Ok(()));
+    }
+
+    #[test]
+    fn validate_reports_forward_reference() {
+        let program = with_steps(json!([
+            { "id": "profit", "description": "", "operation": { "op": "get", "path": "/temp/margin" }, "output_path": "/profit" },
+            { "id": "margin", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" }, "output_path": "/temp/margin" }
+        ]));
+        let errors = Runtime::validate(&program).unwrap_err();
+        assert_eq!(errors, ["Step 'profit' reads '/temp/margin' before it is written by step 'margin'"]);
+    }
+
+    #[test]
+    fn validate_reports_typoed_paths() {
+        let program = with_steps(json!([
+            { "id": "margin", "description": "", "operation": { "op": "subtract", "a": "/revnue", "b": "/costs" }, "output_path": "/temp/margin" },
+            { "id": "profit", "description": "", "operation": { "op": "get", "path": "/temp/margn" }, "output_path": "/profit" }
+        ]));
+        let errors = Runtime::validate(&program).unwrap_err();
+        assert_eq!(errors, [
+            "Step 'margin' reads '/revnue', which is neither an input nor produced by any step",
+            "Step 'profit' reads '/temp/margn', which is neither an input nor produced by any step",
+        ]);
+    }
+
+    #[test]
+    fn runtime_errors_name_the_failing_step() {
+        let program = with_steps(json!([
+            { "id": "calc_margin", "description": "Compute profit margin", "operation": { "op": "get", "path": "/foo" }, "output_path": "/profit" }
+        ]));
+        let err = Runtime::execute(&program, json!({ "revenue": 10, "costs": 0 })).unwrap_err();
+        assert!(
+            err.to_string().starts_with("Runtime Logic Error: Step 'calc_margin' (Compute profit margin): Pointer not found: '/foo'"),
+            "{err}"
+        );
+    }
+
+    /// Evaluate a single op (given as its JSON form) against `inputs`.
+    fn run_op(op: Value, inputs: Value) -> Result<Value, MetaError> {
+        let op: LogicOp = serde_json::from_value(op).unwrap();
+        Runtime::exec_op(&op, &RuntimeState::new(inputs))
+    }
+
+    #[test]
+    fn group_by_sums_per_category() {
+        let inputs = json!({ "transactions": [
+            { "category": "food", "amount": 12.5 },
+            { "category": "rent", "amount": 900 },
+            { "category": "food", "amount": 7.5 },
+            { "amount": 3 }
+        ]});
+        let grouped = run_op(
+            json!({ "op": "group_by", "list_path": "/transactions", "key_field": "category", "aggregate_field": "amount", "aggregation": "sum" }),
+            inputs,
+        ).unwrap();
+        assert_eq!(grouped, json!([
+            { "category": "food", "value": 20.0 },
+            { "category": "rent", "value": 900.0 },
+            { "category": null, "value": 3.0 }
+        ]));
+    }
+
+    fn get(path: &str) -> Value {
+        json!({ "op": "get", "path": path })
+    }
+
+    #[test]
+    fn and_or_short_circuit_before_failing_conditions() {
+        let inputs = json!({ "zero": 0, "one": 1 });
+        // `/missing` would error if evaluated.
+        let and = run_op(json!({ "op": "and", "conditions": [get("/zero"), get("/missing")] }), inputs.clone());
+        assert_eq!(and.unwrap(), json!(false));
+        let or = run_op(json!({ "op": "or", "conditions": [get("/one"), get("/missing")] }), inputs.clone());
+        assert_eq!(or.unwrap(), json!(true));
+        assert!(run_op(json!({ "op": "and", "conditions": [get("/one"), get("/missing")] }), inputs).is_err());
+    }
+
+    #[test]
+    fn boolean_ops_coerce_mixed_truthiness() {
+        let inputs = json!({
+            "null": null, "zero": 0, "empty_str": "", "empty_list": [], "empty_obj": {},
+            "text": "x", "list": [0], "num": -2.5, "obj": { "a": null }
+        });
+        for falsy in ["/null", "/zero", "/empty_str", "/empty_list", "/empty_obj"] {
+            let not = run_op(json!({ "op": "not", "condition": get(falsy) }), inputs.clone());
+            assert_eq!(not.unwrap(), json!(true), "{falsy}");
+        }
+        let all_truthy = json!({ "op": "and", "conditions": [get("/text"), get("/list"), get("/num"), get("/obj")] });
+        assert_eq!(run_op(all_truthy, inputs.clone()).unwrap(), json!(true));
+        let none_truthy = json!({ "op": "or", "conditions": [get("/null"), get("/empty_str"), get("/zero")] });
+        assert_eq!(run_op(none_truthy, inputs).unwrap(), json!(false));
+    }
+
+    fn projects() -> Value {
+        json!({ "projects": [
+            { "name": "Website Redesign", "status": "Active", "revenue": 15000 },
+            { "name": "Mobile App", "status": "active", "revenue": 40000 },
+            { "name": "Consulting", "status": "Closed", "revenue": 5000 },
+            { "name": 42, "status": null, "revenue": 0 }
+        ]})
+    }
+
+    fn names(list: &Value) -> Vec<&str> {
+        list.as_array().unwrap().iter().map(|p| p["name"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn filter_string_contains() {
+        let kept = run_op(
+            json!({ "op": "filter_string", "list_path": "/projects", "field": "name", "operator": "contains", "value": "App" }),
+            projects(),
+        ).unwrap();
+        assert_eq!(names(&kept), ["Mobile App"]);
+    }
+
+    #[test]
+    fn filter_string_eq_ignoring_case() {
+        let op = |ignore_case| json!({
+            "op": "filter_string", "list_path": "/projects", "field": "status", "operator": "eq", "value": "ACTIVE", "ignore_case": ignore_case
+        });
+        assert_eq!(names(&run_op(op(true), projects()).unwrap()), ["Website Redesign", "Mobile App"]);
+        assert_eq!(run_op(op(false), projects()).unwrap(), json!([]));
+    }
+
+    #[test]
+    fn list_ops_read_nested_fields() {
+        let inputs = json!({ "projects": [
+            { "metrics": { "revenue": 100 } },
+            { "metrics": { "revenue": 250.5 } },
+            { "metrics": {} }
+        ]});
+        for field in ["metrics.revenue", "/metrics/revenue"] {
+            let total = run_op(json!({ "op": "sum", "list_path": "/projects", "field": field }), inputs.clone());
+            assert_eq!(total.unwrap(), json!(350.5), "{field}");
+        }
+    }
+
+    #[test]
+    fn build_object_from_computed_paths() {
+        let program = program(
+            json!({}),
+            json!({ "type": "object", "properties": { "report": { "type": "object" } } }),
+            json!([
+                { "id": "profit", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" }, "output_path": "/temp/profit" },
+                { "id": "report", "description": "", "operation": { "op": "build_object", "fields": [
+                    { "key": "profit", "path": "/temp/profit" },
+                    { "key": "revenue", "path": "/revenue" },
+                    { "key": "margin", "path": "/temp/margin" }
+                ] }, "output_path": "/report" }
+            ]),
+        );
+        let output = Runtime::execute(&program, json!({ "revenue": 10, "costs": 4 })).unwrap();
+        assert_eq!(output, json!({ "report": { "profit": 6.0, "revenue": 10, "margin": null } }));
+    }
+
+    #[test]
+    fn format_string_formats_numbers() {
+        let op = json!({
+            "op": "format_string",
+            "template": "Revenue {revenue}, loss {loss}, margin {margin}%, raw {raw}",
+            "variables": [
+                { "key": "revenue", "path": "/revenue", "format": { "decimals": 2, "thousands_separator": true, "prefix": "$" } },
+                { "key": "loss", "path": "/loss", "format": { "decimals": 2, "thousands_separator": true, "prefix": "$" } },
+                { "key": "margin", "path": "/margin", "format": { "decimals": 2 } },
+                { "key": "raw", "path": "/margin" }
+            ]
+        });
+        let text = run_op(op, json!({ "revenue": 1234567.891, "loss": -1234.5, "margin": 27.456 })).unwrap();
+        assert_eq!(text, json!("Revenue $1,234,567.89, loss -$1,234.50, margin 27.46%, raw 27.456"));
+    }
+
+    #[test]
+    fn map_items_computes_two_fields_per_item() {
+        let op = json!({ "op": "map_items", "list_path": "/projects", "steps": [
+            { "output_field": "profit", "operation": { "op": "subtract", "a": "revenue", "b": "costs" } },
+            { "output_field": "margin", "operation": { "op": "percentage", "numerator": "profit", "denominator": "/item/revenue" } }
+        ]});
+        let inputs = json!({ "projects": [{ "revenue": 200, "costs": 150 }, { "revenue": 0, "costs": 10 }] });
+        assert_eq!(run_op(op, inputs).unwrap(), json!([
+            { "revenue": 200, "costs": 150, "profit": 50.0, "margin": 25.0 },
+            { "revenue": 0, "costs": 10, "profit": -10.0, "margin": 0.0 }
+        ]));
+    }
+
+    #[test]
+    fn map_items_binding_does_not_touch_a_real_item_key() {
+        let op = json!({ "op": "map_items", "list_path": "/rows", "steps": [
+            { "output_field": "x2", "operation": { "op": "add", "a": "x", "b": "/item/x" } }
+        ]});
+        let mut state = RuntimeState::new(json!({ "rows": [{ "x": 1 }] }));
+        state.set("/item", json!({ "x": 100 })).unwrap();
+        let op: LogicOp = serde_json::from_value(op).unwrap();
+        assert_eq!(Runtime::exec_op(&op, &state).unwrap(), json!([{ "x": 1, "x2": 2.0 }]));
+        assert_eq!(state.get("/item").unwrap(), json!({ "x": 100 }));
+        // Outside `MapItems`, bare names don't resolve against the `item` key.
+        assert!(state.get("x").is_err());
+    }
+
+    fn error_kind(result: Result<Value, MetaError>) -> RuntimeErrorKind {
+        match result {
+            Err(MetaError::RuntimeError { kind, .. }) => kind,
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn product_multiplies_values_and_is_one_when_empty() {
+        let inputs = json!({ "factors": [2, 3.5, 4], "items": [{ "q": 2 }, { "q": 5 }], "empty": [] });
+        assert_eq!(run_op(json!({ "op": "product", "list_path": "/factors" }), inputs.clone()).unwrap(), json!(28.0));
+        assert_eq!(run_op(json!({ "op": "product", "list_path": "/items", "field": "q" }), inputs.clone()).unwrap(), json!(10.0));
+        assert_eq!(run_op(json!({ "op": "product", "list_path": "/empty" }), inputs).unwrap(), json!(1.0));
+    }
+
+    #[test]
+    fn modulo_keeps_the_dividend_sign_and_rejects_zero() {
+        let inputs = json!({ "a": -7, "b": 3, "zero": 0 });
+        assert_eq!(run_op(json!({ "op": "modulo", "a": "/a", "b": "/b" }), inputs.clone()).unwrap(), json!(-1.0));
+        let by_zero = run_op(json!({ "op": "modulo", "a": "/a", "b": "/zero" }), inputs);
+        assert_eq!(error_kind(by_zero), RuntimeErrorKind::DivisionByZero);
+    }
+
+    #[test]
+    fn power_raises_base_to_exponent() {
+        let inputs = json!({ "base": 2, "exp": 10, "half": 0.5, "nine": 9 });
+        assert_eq!(run_op(json!({ "op": "power", "base": "/base", "exponent": "/exp" }), inputs.clone()).unwrap(), json!(1024.0));
+        assert_eq!(run_op(json!({ "op": "power", "base": "/nine", "exponent": "/half" }), inputs).unwrap(), json!(3.0));
+    }
+
+    #[test]
+    fn distinct_strings_keep_first_seen_order() {
+        let inputs = json!({ "tags": ["b", "a", "b", "c", "a"] });
+        assert_eq!(run_op(json!({ "op": "distinct", "list_path": "/tags" }), inputs.clone()).unwrap(), json!(["b", "a", "c"]));
+        assert_eq!(run_op(json!({ "op": "distinct_count", "list_path": "/tags" }), inputs).unwrap(), json!(3));
+    }
+
+    #[test]
+    fn distinct_objects_by_field() {
+        let inputs = json!({ "orders": [
+            { "customer": "zoe", "id": 1 },
+            { "customer": "amy", "id": 2 },
+            { "customer": "zoe", "id": 3 },
+            { "id": 4 }
+        ]});
+        let op = |name| json!({ "op": name, "list_path": "/orders", "field": "customer" });
+        assert_eq!(run_op(op("distinct"), inputs.clone()).unwrap(), json!(["zoe", "amy"]));
+        assert_eq!(run_op(op("distinct_count"), inputs).unwrap(), json!(2));
+    }
+
+    /// Three steps: `/temp/gross`, `/temp/net` and `/profit`.
+    fn three_step_program() -> AppProgram {
+        with_steps(json!([
+            { "id": "gross", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" }, "output_path": "/temp/gross" },
+            { "id": "net", "description": "", "operation": { "op": "multiply", "a": "/temp/gross", "b": "/rate" }, "output_path": "/temp/net" },
+            { "id": "profit", "description": "", "operation": { "op": "get", "path": "/temp/net" }, "output_path": "/profit" }
+        ]))
+    }
+
+    #[test]
+    fn trace_has_one_entry_per_step() {
+        let (output, trace) = Runtime::execute_traced(&three_step_program(), json!({ "revenue": 10, "costs": 4, "rate": 0.5 })).unwrap();
+        assert_eq!(output, json!({ "profit": 3.0 }));
+        let recorded: Vec<(&str, &str, &Value)> = trace.iter().map(|t| (t.id.as_str(), t.output_path.as_str(), &t.value)).collect();
+        assert_eq!(recorded, [
+            ("gross", "/temp/gross", &json!(6.0)),
+            ("net", "/temp/net", &json!(3.0)),
+            ("profit", "/profit", &json!(3.0)),
+        ]);
+    }
+
+    fn players() -> Value {
+        json!({ "players": [
+            { "name": "cara", "score": 7 },
+            { "name": "abe", "score": 9 },
+            { "name": "bea", "score": 7 },
+            { "name": "dan", "score": 9 }
+        ]})
+    }
+
+    fn field_of<'a>(list: &'a Value, field: &str) -> Vec<&'a Value> {
+        list.as_array().unwrap().iter().map(|item| &item[field]).collect()
+    }
+
+    #[test]
+    fn sort_by_string_field() {
+        let sorted = run_op(json!({ "op": "sort", "list_path": "/players", "field": "name", "descending": false }), players()).unwrap();
+        assert_eq!(names(&sorted), ["abe", "bea", "cara", "dan"]);
+    }
+
+    #[test]
+    fn sort_by_score_desc_then_name_asc() {
+        let op = json!({ "op": "sort", "list_path": "/players", "field": "score", "descending": true, "then_by": "name" });
+        assert_eq!(names(&run_op(op, players()).unwrap()), ["abe", "dan", "bea", "cara"]);
+    }
+
+    #[test]
+    fn descending_sort_keeps_numbers_before_strings() {
+        let inputs = json!({ "rows": [{ "v": "b" }, { "v": 1 }, { "v": "a" }, { "v": 3 }] });
+        let sort = |descending| json!({ "op": "sort", "list_path": "/rows", "field": "v", "descending": descending });
+        assert_eq!(field_of(&run_op(sort(false), inputs.clone()).unwrap(), "v"), [&json!(1), &json!(3), &json!("a"), &json!("b")]);
+        assert_eq!(field_of(&run_op(sort(true), inputs.clone()).unwrap(), "v"), [&json!(3), &json!(1), &json!("b"), &json!("a")]);
+
+        let rank = json!({ "op": "rank", "list_path": "/rows", "field": "v", "output_field": "rank", "descending": true });
+        assert_eq!(field_of(&run_op(rank, inputs).unwrap(), "rank"), [&json!(3), &json!(2), &json!(4), &json!(1)]);
+    }
+
+    #[test]
+    fn coalesce_returns_first_non_null_or_default() {
+        let inputs = json!({ "nickname": null, "name": "Ada" });
+        let op = |paths: Value| json!({ "op": "coalesce", "paths": paths, "default": "anonymous" });
+        assert_eq!(run_op(op(json!(["/missing", "/nickname", "/name"])), inputs.clone()).unwrap(), json!("Ada"));
+        assert_eq!(run_op(op(json!(["/missing", "/nickname"])), inputs).unwrap(), json!("anonymous"));
+    }
+
+    #[test]
+    fn get_falls_back_to_its_default() {
+        let inputs = json!({ "rate": 0.2 });
+        assert_eq!(run_op(json!({ "op": "get", "path": "/rate", "default": 0.1 }), inputs.clone()).unwrap(), json!(0.2));
+        assert_eq!(run_op(json!({ "op": "get", "path": "/missing", "default": 0.1 }), inputs.clone()).unwrap(), json!(0.1));
+        assert_eq!(error_kind(run_op(get("/missing"), inputs)), RuntimeErrorKind::PathNotFound);
+    }
+
+    #[test]
+    fn take_slice_and_reverse() {
+        let inputs = json!({ "xs": [1, 2, 3, 4, 5] });
+        assert_eq!(run_op(json!({ "op": "take", "list_path": "/xs", "count": 10 }), inputs.clone()).unwrap(), json!([1, 2, 3, 4, 5]));
+        assert_eq!(run_op(json!({ "op": "take", "list_path": "/xs", "count": 2 }), inputs.clone()).unwrap(), json!([1, 2]));
+        assert_eq!(run_op(json!({ "op": "slice", "list_path": "/xs", "start": 1, "end": 4 }), inputs.clone()).unwrap(), json!([2, 3, 4]));
+        assert_eq!(run_op(json!({ "op": "slice", "list_path": "/xs", "start": 3, "end": 99 }), inputs.clone()).unwrap(), json!([4, 5]));
+        assert_eq!(run_op(json!({ "op": "slice", "list_path": "/xs", "start": 9 }), inputs.clone()).unwrap(), json!([]));
+        assert_eq!(run_op(json!({ "op": "reverse", "list_path": "/xs" }), inputs).unwrap(), json!([5, 4, 3, 2, 1]));
+    }
+
+    #[test]
+    fn nth_picks_elements_by_index() {
+        let nth = |index: i64, field: Option<&str>| run_op(
+            json!({ "op": "nth", "list_path": "/projects", "index": index, "field": field }),
+            projects(),
+        ).unwrap();
+        assert_eq!(nth(0, Some("name")), json!("Website Redesign"));
+        assert_eq!(nth(-1, Some("revenue")), json!(0));
+        assert_eq!(nth(-1, None), json!({ "name": 42, "status": null, "revenue": 0 }));
+        assert_eq!(nth(4, Some("name")), Value::Null);
+        assert_eq!(nth(-5, None), Value::Null);
+    }
+
+    #[test]
+    fn parse_number_accepts_numeric_strings_and_numbers() {
+        let inputs = json!({ "int": "15000", "float": "12.5", "num": 7, "word": "abc" });
+        let parse = |path: &str| run_op(json!({ "op": "parse_number", "path": path }), inputs.clone());
+        assert_eq!(parse("/int").unwrap(), json!(15000.0));
+        assert_eq!(parse("/float").unwrap(), json!(12.5));
+        assert_eq!(parse("/num").unwrap(), json!(7.0));
+        assert_eq!(error_kind(parse("/word")), RuntimeErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn filter_range_bounds_follow_inclusive() {
+        let inputs = json!({ "xs": [9.99, 10, 15, 20, 20.01] });
+        let range = |inclusive: bool| run_op(
+            json!({ "op": "filter_range", "list_path": "/xs", "min": 10, "max": 20, "inclusive": inclusive }),
+            inputs.clone(),
+        ).unwrap();
+        assert_eq!(range(true), json!([10, 15, 20]));
+        assert_eq!(range(false), json!([15]));
+    }
+
+    #[test]
+    fn clamp_bounds_values() {
+        let inputs = json!({ "low": -5, "min": 0, "mid": 0.5, "max": 1, "high": 7 });
+        let clamp = |path: &str| run_op(json!({ "op": "clamp", "path": path, "min": 0, "max": 1 }), inputs.clone()).unwrap();
+        assert_eq!(clamp("/low"), json!(0.0));
+        assert_eq!(clamp("/min"), json!(0.0));
+        assert_eq!(clamp("/mid"), json!(0.5));
+        assert_eq!(clamp("/max"), json!(1.0));
+        assert_eq!(clamp("/high"), json!(1.0));
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        let inputs = json!({ "odd": [7, 1, 3], "even": [4, 1, 3, 2], "empty": [] });
+        let median = |path: &str| run_op(json!({ "op": "median", "list_path": path }), inputs.clone()).unwrap();
+        assert_eq!(median("/odd"), json!(3.0));
+        assert_eq!(median("/even"), json!(2.5));
+        assert_eq!(median("/empty"), Value::Null);
+    }
+
+    #[test]
+    fn std_dev_population_and_sample() {
+        let inputs = json!({ "xs": [2, 4, 4, 4, 5, 5, 7, 9], "one": [3] });
+        let std_dev = |path: &str, population: bool| run_op(
+            json!({ "op": "std_dev", "list_path": path, "population": population }),
+            inputs.clone(),
+        ).unwrap();
+        assert!((std_dev("/xs", true).as_f64().unwrap() - 2.0).abs() < 1e-9);
+        assert!((std_dev("/xs", false).as_f64().unwrap() - (32.0f64 / 7.0).sqrt()).abs() < 1e-9);
+        assert_eq!(std_dev("/one", false), Value::Null);
+    }
+
+    #[test]
+    fn lookup_maps_through_the_table() {
+        let inputs = json!({ "code": "US", "qty": 3.0, "other": "FR" });
+        let table = json!({ "US": "United States", "3": "three" });
+        let lookup = |path: &str, default: Value| run_op(
+            json!({ "op": "lookup", "path": path, "table": table, "default": default }),
+            inputs.clone(),
+        );
+        assert_eq!(lookup("/code", Value::Null).unwrap(), json!("United States"));
+        assert_eq!(lookup("/qty", Value::Null).unwrap(), json!("three"));
+        assert_eq!(lookup("/other", json!("Unknown")).unwrap(), json!("Unknown"));
+        assert_eq!(error_kind(lookup("/other", Value::Null)), RuntimeErrorKind::LookupMiss);
+    }
+
+    #[test]
+    fn count_where_counts_items_over_a_threshold() {
+        let mut inputs = projects();
+        inputs["empty"] = json!([]);
+        let count = |list_path: &str| run_op(
+            json!({ "op": "count_where", "list_path": list_path, "field": "revenue", "operator": "gt", "value": 10000 }),
+            inputs.clone(),
+        ).unwrap();
+        assert_eq!(count("/projects"), json!(2));
+        assert_eq!(count("/empty"), json!(0));
+    }
+
+    #[test]
+    fn flatten_by_one_level_or_depth() {
+        let inputs = json!({ "nested": [[1, 2], [3, [4, [5]]]], "mixed": [1, [2, 3], "x", []] });
+        let flatten = |path: &str, depth: Option<usize>| run_op(
+            json!({ "op": "flatten", "path": path, "depth": depth }),
+            inputs.clone(),
+        ).unwrap();
+        assert_eq!(flatten("/nested", None), json!([1, 2, 3, [4, [5]]]));
+        assert_eq!(flatten("/nested", Some(2)), json!([1, 2, 3, 4, [5]]));
+        assert_eq!(flatten("/mixed", None), json!([1, 2, 3, "x"]));
+    }
+
+    #[test]
+    fn reduce_folds_from_the_initial_value() {
+        let inputs = json!({ "items": [{ "q": 2 }, { "q": 3 }, { "q": "n/a" }, { "q": 4 }], "divisors": [2, 0] });
+        let reduce = |list_path: &str, field: Option<&str>, operator: &str, initial: f64| run_op(
+            json!({ "op": "reduce", "list_path": list_path, "field": field, "operator": operator, "initial": initial }),
+            inputs.clone(),
+        );
+        assert_eq!(reduce("/items", Some("q"), "add", 10.0).unwrap(), json!(19.0));
+        assert_eq!(reduce("/items", Some("q"), "multiply", 1.0).unwrap(), json!(24.0));
+        assert_eq!(error_kind(reduce("/divisors", None, "divide", 100.0)), RuntimeErrorKind::DivisionByZero);
+    }
+
+    #[test]
+    fn get_f64_parses_numeric_strings() {
+        let state = RuntimeState::new(json!({ "text": "50.0", "num": 7, "padded": "  12.5\n", "flag": true, "word": "abc" }));
+        assert_eq!(get_f64(&state, "/text").unwrap(), 50.0);
+        assert_eq!(get_f64(&state, "/num").unwrap(), 7.0);
+        assert_eq!(get_f64(&state, "/padded").unwrap(), 12.5);
+        for path in ["/flag", "/word"] {
+            let err = get_f64(&state, path).map(Value::from);
+            assert_eq!(error_kind(err), RuntimeErrorKind::TypeMismatch, "{path}");
+        }
+    }
+
+    #[test]
+    fn string_ops_respect_char_boundaries() {
+        let inputs = json!({ "city": "  Zürich Café ", "greek": "ΑΒΓδε", "num": 5 });
+        let op = |op: Value| run_op(op, inputs.clone());
+        assert_eq!(op(json!({ "op": "trim", "path": "/city" })).unwrap(), json!("Zürich Café"));
+        assert_eq!(op(json!({ "op": "to_upper", "path": "/greek" })).unwrap(), json!("ΑΒΓΔΕ"));
+        assert_eq!(op(json!({ "op": "to_lower", "path": "/greek" })).unwrap(), json!("αβγδε"));
+        // Byte offset 5 would split the 'ü'.
+        assert_eq!(op(json!({ "op": "substring", "path": "/city", "start": 3, "length": 4 })).unwrap(), json!("üric"));
+        assert_eq!(op(json!({ "op": "substring", "path": "/greek", "start": 3 })).unwrap(), json!("δε"));
+        assert_eq!(op(json!({ "op": "substring", "path": "/greek", "start": 9 })).unwrap(), json!(""));
+        assert_eq!(error_kind(op(json!({ "op": "to_upper", "path": "/num" }))), RuntimeErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn ratio_and_percentage_yield_zero_for_a_zero_denominator() {
+        let inputs = json!({ "profit": 3000, "revenue": 12000, "zero": 0 });
+        let op = |op: &str, denominator: &str| run_op(
+            json!({ "op": op, "numerator": "/profit", "denominator": denominator }),
+            inputs.clone(),
+        ).unwrap();
+        assert_eq!(op("ratio", "/revenue"), json!(0.25));
+        assert_eq!(op("percentage", "/revenue"), json!(25.0));
+        assert_eq!(op("ratio", "/zero"), json!(0.0));
+        assert_eq!(op("percentage", "/zero"), json!(0.0));
+    }
+
+    #[test]
+    fn validate_reports_duplicate_ids() {
+        let program = with_steps(json!([
+            { "id": "calc", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" }, "output_path": "/temp/margin" },
+            { "id": "calc", "description": "", "operation": { "op": "get", "path": "/temp/margin" }, "output_path": "/profit" }
+        ]));
+        let errors = Runtime::validate(&program).unwrap_err();
+        assert_eq!(errors, ["Duplicate step id 'calc' (already used by the step writing '/temp/margin')"]);
+    }
+
+    #[test]
+    fn validate_reports_duplicate_output_paths() {
+        let program = with_steps(json!([
+            { "id": "gross", "description": "", "operation": { "op": "get", "path": "/revenue" }, "output_path": "/profit" },
+            { "id": "net", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" }, "output_path": "/profit" }
+        ]));
+        let errors = Runtime::validate(&program).unwrap_err();
+        assert_eq!(errors, ["Step 'net' overwrites '/profit', already written by step 'gross'"]);
+    }
+
+    #[test]
+    fn execute_partial_skips_a_failing_middle_step() {
+        let program = program(
+            json!({ "type": "object" }),
+            json!({ "type": "object", "properties": { "total": {}, "per_unit": {}, "count": {} } }),
+            json!([
+                { "id": "total", "description": "", "operation": { "op": "sum", "list_path": "/xs" }, "output_path": "/total" },
+                { "id": "per_unit", "description": "", "operation": { "op": "divide", "a": "/total", "b": "/units" }, "output_path": "/per_unit" },
+                { "id": "count", "description": "", "operation": { "op": "count", "list_path": "/xs" }, "output_path": "/count" }
+            ]),
+        );
+        let inputs = json!({ "xs": [1, 2, 3], "units": 0 });
+        let (output, errors) = Runtime::execute_partial(&program, inputs.clone());
+        assert_eq!(output["total"], json!(6.0));
+        assert_eq!(output["count"], json!(3));
+        assert!(output.get("per_unit").is_none_or(Value::is_null), "{output}");
+        let [error] = &errors[..] else { panic!("{errors:?}") };
+        assert_eq!((error.id.as_str(), error.output_path.as_str()), ("per_unit", "/per_unit"));
+        assert!(error.error.contains("Step 'per_unit'"), "{}", error.error);
+        assert!(Runtime::execute(&program, inputs).is_err());
+    }
+
+    #[test]
+    fn execute_validated_rejects_wrong_typed_output() {
+        let program = with_steps(json!([
+            { "id": "label", "description": "", "operation": { "op": "constant", "value": "lots" }, "output_path": "/profit" }
+        ]));
+        let inputs = json!({ "revenue": 10, "costs": 3 });
+        assert_eq!(Runtime::execute(&program, inputs.clone()).unwrap(), json!({ "profit": "lots" }));
+        let err = Runtime::execute_validated(&program, inputs).unwrap_err();
+        assert!(matches!(&err, MetaError::ValidationFailed(msg) if msg.starts_with("Invalid output: /profit: expected number")), "{err}");
+    }
+
+    #[test]
+    fn abs_negate_and_sign() {
+        let inputs = json!({ "neg": -4.5, "zero": 0, "pos": 3, "word": "abc" });
+        let op = |op: &str, path: &str| run_op(json!({ "op": op, "path": path }), inputs.clone());
+        assert_eq!(op("abs", "/neg").unwrap(), json!(4.5));
+        assert_eq!(op("abs", "/zero").unwrap(), json!(0.0));
+        assert_eq!(op("negate", "/neg").unwrap(), json!(4.5));
+        assert_eq!(op("negate", "/pos").unwrap(), json!(-3.0));
+        assert_eq!(op("sign", "/neg").unwrap(), json!(-1));
+        assert_eq!(op("sign", "/zero").unwrap(), json!(0));
+        assert_eq!(op("sign", "/pos").unwrap(), json!(1));
+        for name in ["abs", "negate", "sign"] {
+            assert_eq!(error_kind(op(name, "/word")), RuntimeErrorKind::TypeMismatch, "{name}");
+        }
+    }
+
+    #[test]
+    fn merge_arrays_and_append() {
+        let inputs = json!({ "a": [1, 2], "b": [{ "x": 3 }], "empty": [], "scalar": 5 });
+        let merge = |paths: Value| run_op(json!({ "op": "merge_arrays", "paths": paths }), inputs.clone());
+        assert_eq!(merge(json!(["/a", "/empty", "/b"])).unwrap(), json!([1, 2, { "x": 3 }]));
+        assert_eq!(error_kind(merge(json!(["/a", "/scalar"]))), RuntimeErrorKind::TypeMismatch);
+
+        let append = |list_path: &str| run_op(json!({ "op": "append", "list_path": list_path, "value": 9.5 }), inputs.clone());
+        assert_eq!(append("/a").unwrap(), json!([1, 2, 9.5]));
+        assert_eq!(append("/empty").unwrap(), json!([9.5]));
+        assert_eq!(error_kind(append("/scalar")), RuntimeErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn index_by_keys_projects_by_name() {
+        let mut inputs = projects();
+        inputs["projects"].as_array_mut().unwrap().extend([
+            json!({ "status": "no name" }),
+            json!({ "name": "Consulting", "status": "Reopened", "revenue": 7000 }),
+        ]);
+        let indexed = run_op(json!({ "op": "index_by", "list_path": "/projects", "key_field": "name" }), inputs).unwrap();
+        let keys: Vec<&String> = indexed.as_object().unwrap().keys().collect();
+        assert_eq!(keys.len(), 4, "{indexed}");
+        assert_eq!(indexed["Mobile App"]["revenue"], json!(40000));
+        assert_eq!(indexed["42"]["revenue"], json!(0));
+        // The later duplicate wins; the item without a name is skipped.
+        assert_eq!(indexed["Consulting"]["status"], "Reopened");
+    }
+
+    #[test]
+    fn missing_pointer_is_a_path_not_found_error() {
+        let program = with_steps(json!([
+            { "id": "calc_margin", "description": "Compute profit margin", "operation": { "op": "get", "path": "/temp/margin" }, "output_path": "/profit" }
+        ]));
+        let err = Runtime::execute(&program, json!({ "revenue": 10, "costs": 0 })).unwrap_err();
+        let MetaError::RuntimeError { kind, path, step_id, step_description, .. } = err else { panic!("{err:?}") };
+        assert_eq!(kind, RuntimeErrorKind::PathNotFound);
+        assert_eq!(path.as_deref(), Some("/temp/margin"));
+        assert_eq!(step_id.as_deref(), Some("calc_margin"));
+        assert_eq!(step_description.as_deref(), Some("Compute profit margin"));
+    }
+
+    #[test]
+    fn execute_coerced_parses_number_typed_outputs() {
+        let program = with_steps(json!([
+            { "id": "profit", "description": "", "operation": { "op": "get", "path": "/revenue" }, "output_path": "/profit" }
+        ]));
+        let inputs = json!({ "revenue": "12.5", "costs": 0 });
+        assert_eq!(Runtime::execute(&program, inputs.clone()).unwrap(), json!({ "profit": "12.5" }));
+        assert_eq!(Runtime::execute_coerced(&program, inputs).unwrap(), json!({ "profit": 12.5 }));
+
+        let err = Runtime::execute_coerced(&program, json!({ "revenue": "lots", "costs": 0 })).unwrap_err();
+        assert!(matches!(&err, MetaError::RuntimeError { kind: RuntimeErrorKind::TypeMismatch, path: Some(p), .. } if p == "/profit"), "{err}");
+    }
+
+    #[test]
+    fn chain_evaluates_an_expression_in_one_step() {
+        let inputs = json!({ "revenue": 100, "costs": 40, "margin": 0.5, "units": 3 });
+        // ((revenue - costs) * margin) / units
+        let chain = json!({ "op": "chain", "ops": [
+            { "op": "subtract", "a": "/revenue", "b": "/costs" },
+            { "op": "multiply", "a": "/_0", "b": "/margin" },
+            { "op": "divide", "a": "/_1", "b": "/units" }
+        ]});
+        assert_eq!(run_op(chain, inputs.clone()).unwrap(), json!(10.0));
+
+        // A nested chain restarts at `/_0`, shadowing the outer results.
+        let nested = json!({ "op": "chain", "ops": [
+            { "op": "get", "path": "/revenue" },
+            { "op": "chain", "ops": [
+                { "op": "get", "path": "/costs" },
+                { "op": "add", "a": "/_0", "b": "/_0" }
+            ]},
+            { "op": "subtract", "a": "/_0", "b": "/_1" }
+        ]});
+        assert_eq!(run_op(nested, inputs).unwrap(), json!(20.0));
+    }
+
+    #[test]
+    fn running_total_accumulates_in_list_order() {
+        let inputs = json!({ "sales": [
+            { "day": 1, "revenue": 100 },
+            { "day": 2, "revenue": "n/a" },
+            { "day": 3, "revenue": 50.5 },
+            7
+        ]});
+        let totals = run_op(
+            json!({ "op": "running_total", "list_path": "/sales", "field": "revenue", "output_field": "to_date" }),
+            inputs,
+        ).unwrap();
+        assert_eq!(totals, json!([
+            { "day": 1, "revenue": 100, "to_date": 100.0 },
+            { "day": 2, "revenue": "n/a", "to_date": 100.0 },
+            { "day": 3, "revenue": 50.5, "to_date": 150.5 },
+            7
+        ]));
+    }
+
+    #[test]
+    fn nested_output_schema_is_assembled_from_leaf_paths() {
+        let program = program(
+            json!({ "type": "object" }),
+            json!({ "type": "object", "properties": {
+                "summary": { "type": "object", "properties": { "total": { "type": "number" }, "count": { "type": "integer" } } },
+                "max": { "type": "number" }
+            }}),
+            json!([
+                { "id": "total", "description": "", "operation": { "op": "sum", "list_path": "/xs" }, "output_path": "/summary/total" },
+                { "id": "count", "description": "", "operation": { "op": "count", "list_path": "/xs" }, "output_path": "/summary/count" },
+                { "id": "max", "description": "", "operation": { "op": "max", "list_path": "/xs" }, "output_path": "/max" }
+            ]),
+        );
+        let output = Runtime::execute(&program, json!({ "xs": [4, 1, 7] })).unwrap();
+        assert_eq!(output, json!({ "summary": { "total": 12.0, "count": 3 }, "max": 7.0 }));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn date_diff_and_format() {
+        let inputs = json!({ "start": "2024-02-27", "end": "2024-03-02T12:00:00Z", "stamp": "2024-03-01T09:30:00+02:00", "bad": "March 1st" });
+        let diff = |end: &str, unit: &str| run_op(
+            json!({ "op": "date_diff", "start_path": "/start", "end_path": end, "unit": unit }),
+            inputs.clone(),
+        );
+        assert_eq!(diff("/end", "days").unwrap(), json!(4.5));
+        assert_eq!(diff("/end", "hours").unwrap(), json!(108.0));
+
+        let format = |path: &str| run_op(json!({ "op": "date_format", "path": path, "format": "%Y-%m-%d" }), inputs.clone());
+        assert_eq!(format("/stamp").unwrap(), json!("2024-03-01"));
+
+        let err = format("/bad").unwrap_err();
+        assert!(err.to_string().contains("'March 1st'"), "{err}");
+        assert_eq!(error_kind(diff("/bad", "days")), RuntimeErrorKind::TypeMismatch);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_extract_match_and_replace() {
+        let inputs = json!({ "ref": "Invoice INV-2024-017 (paid)", "plain": "no digits here" });
+        let op = |op: Value| run_op(op, inputs.clone()).unwrap();
+        assert_eq!(op(json!({ "op": "regex_extract", "path": "/ref", "pattern": r"INV-(\d{4})-(\d+)", "group": 2 })), json!("017"));
+        assert_eq!(op(json!({ "op": "regex_extract", "path": "/plain", "pattern": r"\d+", "group": 0 })), Value::Null);
+        assert_eq!(op(json!({ "op": "regex_match", "path": "/ref", "pattern": r"\(paid\)$" })), json!(true));
+        assert_eq!(op(json!({ "op": "regex_match", "path": "/plain", "pattern": r"\d" })), json!(false));
+        assert_eq!(
+            op(json!({ "op": "regex_replace", "path": "/ref", "pattern": r"INV-(\d{4})-(\d+)", "replacement": "$2/$1" })),
+            json!("Invoice 017/2024 (paid)")
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn bad_regex_fails_before_any_step_runs() {
+        struct Steps(std::sync::Mutex<usize>);
+        impl Listener for Steps {
+            fn on_step(&self, _step: &LogicStep, _value: &Value) {
+                *self.0.lock().unwrap() += 1;
+            }
+        }
+
+        let program = with_steps(json!([
+            { "id": "profit", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" }, "output_path": "/profit" },
+            { "id": "tag", "description": "", "operation": { "op": "regex_match", "path": "/name", "pattern": "(unclosed" }, "output_path": "/tag" }
+        ]));
+        let steps = Steps(std::sync::Mutex::new(0));
+        let err = Runtime::execute_with_listener(&program, json!({ "revenue": 10, "costs": 3, "name": "x" }), &steps).unwrap_err();
+        assert!(matches!(&err, MetaError::RuntimeError { kind: RuntimeErrorKind::InvalidArgument, step_id: Some(id), .. } if id == "tag"), "{err}");
+        assert_eq!(*steps.0.lock().unwrap(), 0);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn bad_regex_only_skips_its_step_in_partial_runs() {
+        let program = with_steps(json!([
+            { "id": "tag", "description": "", "operation": { "op": "regex_match", "path": "/name", "pattern": "(unclosed" }, "output_path": "/tag" },
+            { "id": "codes", "description": "", "operation": { "op": "map_items", "list_path": "/refs", "steps": [
+                { "output_field": "n", "operation": { "op": "regex_extract", "path": "/item/ref", "pattern": r"\d+", "group": 0 } }
+            ] }, "output_path": "/profit" }
+        ]));
+        let inputs = json!({ "name": "x", "refs": [{ "ref": "A-1" }, { "ref": "B-22" }] });
+        let (output, errors) = Runtime::execute_partial(&program, inputs);
+        assert_eq!(errors.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), ["tag"]);
+        assert_eq!(field_of(&output["profit"], "n"), [&json!("1"), &json!("22")]);
+    }
+
+    #[test]
+    fn pluck_nested_field_with_and_without_default() {
+        let inputs = json!({ "projects": [
+            { "metrics": { "revenue": 100 } },
+            { "metrics": {} },
+            { "name": "no metrics" },
+            { "metrics": { "revenue": null } }
+        ]});
+        let pluck = |default: Value| run_op(
+            json!({ "op": "pluck", "path": "/projects", "key": "metrics.revenue", "default": default }),
+            inputs.clone(),
+        ).unwrap();
+        assert_eq!(pluck(Value::Null), json!([100, null, null, null]));
+        assert_eq!(pluck(json!(0)), json!([100, 0.0, 0.0, 0.0]));
+        let flat = run_op(json!({ "op": "pluck", "path": "/projects", "key": "metrics" }), inputs).unwrap();
+        assert_eq!(flat[0], json!({ "revenue": 100 }));
+    }
+
+    #[test]
+    fn execute_timed_reports_each_step() {
+        let program = three_step_program();
+        let inputs = json!({ "revenue": 100, "costs": 30, "rate": 0.2 });
+        let (output, metrics) = Runtime::execute_timed(&program, inputs.clone()).unwrap();
+        assert_eq!(output, Runtime::execute(&program, inputs).unwrap());
+        let ids: Vec<&str> = metrics.per_step.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["gross", "net", "profit"]);
+        let parts: Duration = metrics.per_step.iter().map(|(_, d)| *d).sum();
+        assert!(parts <= metrics.total, "{parts:?} > {:?}", metrics.total);
+    }
+
+    #[test]
+    fn drop_nulls_from_objects_and_scalars() {
+        let inputs = json!({
+            "rows": [{ "v": 1 }, { "v": null }, { "w": 2 }, { "v": 0 }],
+            "values": [1, null, 2, null]
+        });
+        let drop = |list_path: &str, field: Option<&str>| run_op(
+            json!({ "op": "drop_nulls", "list_path": list_path, "field": field }),
+            inputs.clone(),
+        ).unwrap();
+        assert_eq!(drop("/rows", Some("v")), json!([{ "v": 1 }, { "v": 0 }]));
+        assert_eq!(drop("/values", None), json!([1, 2]));
+    }
+
+    #[test]
+    fn collect_reads_a_field_across_a_list() {
+        let collect = |path: &str| run_op(json!({ "op": "collect", "path": path }), projects()).unwrap();
+        assert_eq!(collect("/projects/*/revenue"), json!([15000, 40000, 5000, 0]));
+        assert_eq!(collect("/inputs/projects/*/revenue"), json!([15000, 40000, 5000, 0]));
+        assert_eq!(collect("/projects/*/status"), json!(["Active", "active", "Closed", null]));
+        assert_eq!(collect("/projects/*/budget"), json!([]));
+    }
+
+    #[test]
+    fn moving_average_over_a_window_of_three() {
+        let inputs = json!({ "days": [{ "v": 3 }, { "v": 6 }, { "v": "n/a" }, { "v": 9 }, { "v": 12 }] });
+        let average = |window: usize| run_op(
+            json!({ "op": "moving_average", "list_path": "/days", "field": "v", "window": window }),
+            inputs.clone(),
+        );
+        assert_eq!(average(3).unwrap(), json!([null, null, 6.0, 9.0]));
+        assert_eq!(average(1).unwrap(), json!([3.0, 6.0, 9.0, 12.0]));
+        assert_eq!(average(5).unwrap(), json!([null, null, null, null]));
+        assert_eq!(error_kind(average(0)), RuntimeErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn execute_parallel_matches_sequential_execution() {
+        let program = program(
+            json!({ "type": "object" }),
+            json!({ "type": "object", "properties": { "total": {}, "largest": {}, "spread": {} } }),
+            json!([
+                { "id": "total", "description": "", "operation": { "op": "sum", "list_path": "/xs" }, "output_path": "/total" },
+                { "id": "max", "description": "", "operation": { "op": "max", "list_path": "/xs" }, "output_path": "/largest" },
+                { "id": "min", "description": "", "operation": { "op": "min", "list_path": "/ys" }, "output_path": "/temp/min" },
+                { "id": "spread", "description": "", "operation": { "op": "subtract", "a": "/largest", "b": "/temp/min" }, "output_path": "/spread" }
+            ]),
+        );
+        let waves = step_waves(&program.steps);
+        assert_eq!(waves, [vec![0, 1, 2], vec![3]]);
+
+        let inputs = json!({ "xs": [4, 9, 2], "ys": [7, -1] });
+        let sequential = Runtime::execute(&program, inputs.clone()).unwrap();
+        assert_eq!(sequential, json!({ "total": 15.0, "largest": 9.0, "spread": 10.0 }));
+        assert_eq!(Runtime::execute_parallel(&program, inputs).unwrap(), sequential);
+
+        let profit: AppProgram = serde_json::from_str(include_str!("../../fixtures/profit-program.json")).unwrap();
+        let inputs: Value = serde_json::from_str(include_str!("../../fixtures/profit-input.json")).unwrap();
+        assert_eq!(Runtime::execute_parallel(&profit, inputs.clone()).unwrap(), Runtime::execute(&profit, inputs).unwrap());
+    }
+
+    #[test]
+    fn weighted_average_of_nested_fields() {
+        let inputs = json!({
+            "grades": [
+                { "score": { "value": 90 }, "credits": 3 },
+                { "score": { "value": 70 }, "credits": 1 },
+                { "score": {}, "credits": 5 }
+            ],
+            "weightless": [{ "score": { "value": 80 }, "credits": 0 }]
+        });
+        let average = |list_path: &str| run_op(
+            json!({ "op": "weighted_average", "list_path": list_path, "value_field": "score.value", "weight_field": "credits" }),
+            inputs.clone(),
+        ).unwrap();
+        assert_eq!(average("/grades"), json!(85.0));
+        assert_eq!(average("/weightless"), Value::Null);
+    }
+
+    #[test]
+    fn zip_pairs_by_index_up_to_the_shorter_list() {
+        let inputs = json!({ "names": ["abe", "bea", "cara"], "scores": [9, 7, 8], "two": [1, 2] });
+        let zip = |right: &str| run_op(
+            json!({ "op": "zip", "left_path": "/names", "right_path": right, "left_key": "name", "right_key": "score" }),
+            inputs.clone(),
+        ).unwrap();
+        assert_eq!(zip("/scores"), json!([
+            { "name": "abe", "score": 9 },
+            { "name": "bea", "score": 7 },
+            { "name": "cara", "score": 8 }
+        ]));
+        assert_eq!(zip("/two"), json!([{ "name": "abe", "score": 1 }, { "name": "bea", "score": 2 }]));
+    }
+
+    #[test]
+    fn output_warnings_name_unwritten_properties() {
+        let program = program(
+            json!({ "type": "object" }),
+            json!({ "type": "object", "properties": {
+                "profit": { "type": "number" },
+                "profit_margin": { "type": "number" },
+                "summary": { "type": "object", "properties": { "best": { "type": "string" } } }
+            }}),
+            json!([
+                { "id": "profit", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" }, "output_path": "/profit" },
+                { "id": "best", "description": "", "operation": { "op": "constant", "value": "A" }, "output_path": "/temp/best" }
+            ]),
+        );
+        let output = Runtime::execute(&program, json!({ "revenue": 10, "costs": 4 })).unwrap();
+        let warnings = Runtime::output_warnings(&program, &output);
+        let found: Vec<(&str, &str)> = warnings.iter().map(|w| (w.property.as_str(), w.message.as_str())).collect();
+        assert_eq!(found, [
+            ("profit_margin", "property `profit_margin` was never written by any step"),
+            ("summary", "property `summary` was never written by any step"),
+        ]);
+
+        let output = json!({ "profit": 6.0, "profit_margin": 0.6, "summary": {} });
+        let [warning] = &Runtime::output_warnings(&program, &output)[..] else { panic!() };
+        assert_eq!(warning.property, "summary/best");
+        assert!(warning.message.contains("step 'best' writes '/temp/best'"), "{}", warning.message);
+    }
+
+    #[test]
+    fn chunk_splits_into_fixed_size_groups() {
+        let inputs = json!({ "six": [1, 2, 3, 4, 5, 6], "five": [1, 2, 3, 4, 5], "empty": [] });
+        let chunk = |list_path: &str, size: usize| run_op(json!({ "op": "chunk", "list_path": list_path, "size": size }), inputs.clone());
+        assert_eq!(chunk("/six", 3).unwrap(), json!([[1, 2, 3], [4, 5, 6]]));
+        assert_eq!(chunk("/five", 2).unwrap(), json!([[1, 2], [3, 4], [5]]));
+        assert_eq!(chunk("/empty", 2).unwrap(), json!([]));
+        assert_eq!(error_kind(chunk("/six", 0)), RuntimeErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn format_list_renders_each_item() {
+        let summary = run_op(
+            json!({ "op": "format_list", "list_path": "/projects", "item_template": "{name} (${revenue}){budget}", "separator": ", " }),
+            projects(),
+        ).unwrap();
+        assert_eq!(summary, json!("Website Redesign ($15000), Mobile App ($40000), Consulting ($5000), 42 ($0)"));
+    }
+
+    #[test]
+    fn normalize_scales_into_unit_range() {
+        let inputs = json!({
+            "scores": [{ "v": 10 }, { "v": 20 }, { "v": 15 }, { "x": 1 }],
+            "flat": [{ "v": 4 }, { "v": 4 }]
+        });
+        let normalize = |list_path: &str| run_op(
+            json!({ "op": "normalize", "list_path": list_path, "field": "v", "output_field": "scaled" }),
+            inputs.clone(),
+        ).unwrap();
+        assert_eq!(field_of(&normalize("/scores"), "scaled"), [&json!(0.0), &json!(1.0), &json!(0.5), &Value::Null]);
+        assert_eq!(field_of(&normalize("/flat"), "scaled"), [&json!(0.0), &json!(0.0)]);
+    }
+
+    #[test]
+    fn cast_to_each_target_type() {
+        let inputs = json!({
+            "big": 12000000.0, "frac": 0.1, "yes": true, "none": null, "list": [1, "a"],
+            "numeric": " 12.5 ", "word": "twelve", "upper_no": "NO", "zero": 0
+        });
+        let cast = |path: &str, target: &str| run_op(json!({ "op": "cast", "path": path, "target": target }), inputs.clone());
+
+        assert_eq!(cast("/big", "string").unwrap(), json!("12000000"));
+        assert_eq!(cast("/frac", "string").unwrap(), json!("0.1"));
+        assert_eq!(cast("/yes", "string").unwrap(), json!("true"));
+        assert_eq!(cast("/none", "string").unwrap(), json!(""));
+        assert_eq!(cast("/list", "string").unwrap(), json!(r#"[1,"a"]"#));
+
+        assert_eq!(cast("/numeric", "number").unwrap(), json!(12.5));
+        assert_eq!(cast("/yes", "number").unwrap(), json!(1));
+        assert_eq!(error_kind(cast("/word", "number")), RuntimeErrorKind::TypeMismatch);
+
+        assert_eq!(cast("/upper_no", "bool").unwrap(), json!(false));
+        assert_eq!(cast("/zero", "bool").unwrap(), json!(false));
+        assert_eq!(cast("/big", "bool").unwrap(), json!(true));
+        assert_eq!(cast("/none", "bool").unwrap(), json!(false));
+        assert_eq!(error_kind(cast("/word", "bool")), RuntimeErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn rank_shares_ties_and_skips_the_next_rank() {
+        let mut inputs = players();
+        inputs["players"].as_array_mut().unwrap().push(json!({ "name": "eve", "score": 3 }));
+        let rank = |descending: bool| run_op(
+            json!({ "op": "rank", "list_path": "/players", "field": "score", "output_field": "rank", "descending": descending }),
+            inputs.clone(),
+        ).unwrap();
+        let ranked = rank(true);
+        assert_eq!(names(&ranked), ["cara", "abe", "bea", "dan", "eve"]);
+        assert_eq!(field_of(&ranked, "rank"), [&json!(3), &json!(1), &json!(3), &json!(1), &json!(5)]);
+        assert_eq!(field_of(&rank(false), "rank"), [&json!(2), &json!(4), &json!(2), &json!(4), &json!(1)]);
+    }
+
+    #[test]
+    fn merge_objects_shallow_and_deep() {
+        let inputs = json!({
+            "base": { "name": "A", "totals": { "revenue": 10, "costs": 4 } },
+            "update": { "totals": { "costs": 5 }, "status": "done" },
+            "list": [1]
+        });
+        let merge = |deep: bool, paths: Value| run_op(json!({ "op": "merge_objects", "paths": paths, "deep": deep }), inputs.clone());
+        assert_eq!(
+            merge(false, json!(["/base", "/update"])).unwrap(),
+            json!({ "name": "A", "totals": { "costs": 5 }, "status": "done" })
+        );
+        assert_eq!(
+            merge(true, json!(["/base", "/update"])).unwrap(),
+            json!({ "name": "A", "totals": { "revenue": 10, "costs": 5 }, "status": "done" })
+        );
+        assert_eq!(error_kind(merge(true, json!(["/base", "/list"]))), RuntimeErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn divide_applies_each_zero_policy() {
+        let inputs = json!({ "a": 6, "neg": -6, "zero": 0, "b": 4 });
+        let divide = |a: &str, b: &str, on_zero: Option<&str>| run_op(
+            json!({ "op": "divide", "a": a, "b": b, "on_zero": on_zero }),
+            inputs.clone(),
+        );
+        assert_eq!(divide("/a", "/b", None).unwrap(), json!(1.5));
+        assert_eq!(error_kind(divide("/a", "/zero", None)), RuntimeErrorKind::DivisionByZero);
+        assert_eq!(error_kind(divide("/a", "/zero", Some("error"))), RuntimeErrorKind::DivisionByZero);
+        assert_eq!(divide("/a", "/zero", Some("zero")).unwrap(), json!(0.0));
+        assert_eq!(divide("/a", "/zero", Some("null")).unwrap(), Value::Null);
+        assert_eq!(divide("/a", "/zero", Some("infinity")).unwrap(), json!("Infinity"));
+        assert_eq!(divide("/neg", "/zero", Some("infinity")).unwrap(), json!("-Infinity"));
+        assert_eq!(divide("/zero", "/zero", Some("infinity")).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn calculate_divide_defaults_to_zero_and_honours_the_policy() {
+        let inputs = json!({ "rows": [{ "a": 6, "b": 0 }, { "a": 6, "b": 3 }] });
+        let calculate = |on_zero: Option<&str>| run_op(
+            json!({ "op": "calculate", "list_path": "/rows", "output_field": "q", "operator": "divide", "a_field": "a", "b_field": "b", "on_zero": on_zero }),
+            inputs.clone(),
+        );
+        assert_eq!(field_of(&calculate(None).unwrap(), "q"), [&json!(0.0), &json!(2.0)]);
+        assert_eq!(field_of(&calculate(Some("infinity")).unwrap(), "q"), [&json!("Infinity"), &json!(2.0)]);
+        assert_eq!(error_kind(calculate(Some("error"))), RuntimeErrorKind::DivisionByZero);
+    }
+
+    #[test]
+    fn infinity_sentinel_is_not_a_number_operand() {
+        let state = RuntimeState::new(json!({ "ratio": "Infinity", "label": "-Infinity" }));
+        for path in ["/ratio", "/label"] {
+            assert_eq!(error_kind(get_f64(&state, path).map(Value::from)), RuntimeErrorKind::NonFinite, "{path}");
+        }
+    }
+
+    #[test]
+    fn histogram_counts_boundaries_in_the_upper_bucket() {
+        let inputs = json!({ "ages": [5, 10, 15, 20, 25, 30, 40, "n/a"] });
+        let histogram = |buckets: Value| run_op(
+            json!({ "op": "histogram", "list_path": "/ages", "buckets": buckets }),
+            inputs.clone(),
+        ).unwrap();
+        // [<10, 10..20, 20..30, >=30]
+        assert_eq!(histogram(json!([10, 20, 30])), json!([1, 2, 2, 2]));
+        assert_eq!(histogram(json!([30, 10, 20])), json!([1, 2, 2, 2]));
+        assert_eq!(histogram(json!([])), json!([7]));
+    }
+
+    #[test]
+    fn pivot_sums_sales_by_region_and_quarter() {
+        let inputs = json!({ "sales": [
+            { "region": "north", "quarter": "Q1", "amount": 100 },
+            { "region": "south", "quarter": "Q1", "amount": 50 },
+            { "region": "north", "quarter": "Q2", "amount": 70 },
+            { "region": "north", "quarter": "Q1", "amount": 30 },
+            { "region": "south", "amount": 999 }
+        ]});
+        let pivot = |aggregation: &str| run_op(
+            json!({ "op": "pivot", "list_path": "/sales", "row_field": "region", "col_field": "quarter", "value_field": "amount", "aggregation": aggregation }),
+            inputs.clone(),
+        ).unwrap();
+        assert_eq!(pivot("sum"), json!([
+            { "region": "north", "Q1": 130.0, "Q2": 70.0 },
+            { "region": "south", "Q1": 50.0, "Q2": 0.0 }
+        ]));
+        assert_eq!(pivot("max")[1], json!({ "region": "south", "Q1": 50.0, "Q2": null }));
+    }
+
+    #[test]
+    fn trace_file_round_trips_in_step_order() {
+        let path = std::env::temp_dir().join(format!("meta-ai-trace-{}.json", uuid::Uuid::new_v4()));
+        let inputs = json!({ "revenue": 10, "costs": 4, "rate": 0.5 });
+        let output = Runtime::execute_traced_to_file(&three_step_program(), inputs, &path).unwrap();
+        assert_eq!(output, json!({ "profit": 3.0 }));
+
+        let trace: Vec<StepTrace> = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let recorded: Vec<(&str, &Value)> = trace.iter().map(|t| (t.id.as_str(), &t.value)).collect();
+        assert_eq!(recorded, [("gross", &json!(6.0)), ("net", &json!(3.0)), ("profit", &json!(3.0))]);
+
+        let again: Vec<StepTrace> = serde_json::from_value(serde_json::to_value(&trace).unwrap()).unwrap();
+        assert_eq!(again.iter().map(|t| t.duration).collect::<Vec<_>>(), trace.iter().map(|t| t.duration).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn equals_and_json_diff() {
+        let inputs = json!({
+            "int": 1, "float": 1.0, "two": 2,
+            "a": { "name": "x", "totals": { "revenue": 100, "tags": ["p", "q"] } },
+            "b": { "totals": { "tags": ["p"], "revenue": 120.0 }, "name": "x", "extra": true }
+        });
+        let equals = |a: &str, b: &str| run_op(json!({ "op": "equals", "a": a, "b": b }), inputs.clone()).unwrap();
+        let diff = |a: &str, b: &str| run_op(json!({ "op": "json_diff", "a": a, "b": b }), inputs.clone()).unwrap();
+
+        assert_eq!(equals("/int", "/float"), json!(true));
+        assert_eq!(equals("/a", "/a"), json!(true));
+        assert_eq!(equals("/a", "/b"), json!(false));
+
+        assert_eq!(diff("/int", "/float"), json!([]));
+        assert_eq!(diff("/int", "/two"), json!([{ "path": "", "a": 1, "b": 2 }]));
+        assert_eq!(diff("/a", "/b"), json!([
+            { "path": "/totals/revenue", "a": 100, "b": 120.0 },
+            { "path": "/totals/tags/1", "a": "q" },
+            { "path": "/extra", "b": true }
+        ]));
+    }
+
+    #[test]
+    fn rename_keys_and_transform_keys_to_camel() {
+        let inputs = json!({ "record": {
+            "total_revenue": 100,
+            "old": 1,
+            "line_items": [{ "unit_price": 5 }],
+            "meta_data": { "created_at": "today" }
+        }});
+
+        let renamed = run_op(
+            json!({ "op": "rename_keys", "path": "/record", "mapping": { "old": "new", "total_revenue": "revenue" } }),
+            inputs.clone(),
+        ).unwrap();
+        assert_eq!(renamed["new"], 1);
+        assert_eq!(renamed["revenue"], 100);
+        assert!(renamed.get("old").is_none() && renamed.get("total_revenue").is_none());
+        assert_eq!(renamed["meta_data"], inputs["record"]["meta_data"]);
+
+        let camel = |recursive: bool| run_op(
+            json!({ "op": "transform_keys", "path": "/record", "style": "camel", "recursive": recursive }),
+            inputs.clone(),
+        ).unwrap();
+        let shallow = camel(false);
+        assert_eq!(shallow["totalRevenue"], 100);
+        assert_eq!(shallow["metaData"], json!({ "created_at": "today" }));
+        let deep = camel(true);
+        assert_eq!(deep["metaData"], json!({ "createdAt": "today" }));
+        assert_eq!(deep["lineItems"], json!([{ "unitPrice": 5 }]));
+    }
+
+    #[test]
+    fn contains_checks_array_membership_and_substrings() {
+        let inputs = json!({ "tags": ["urgent", 3, { "id": 1 }], "title": "Q3 report", "three": 3.0, "obj": { "id": 1 }, "word": "report", "other": "draft", "n": 5 });
+        let contains = |haystack: &str, needle: &str| run_op(json!({ "op": "contains", "haystack": haystack, "needle": needle }), inputs.clone());
+        assert_eq!(contains("/tags", "/three").unwrap(), json!(true));
+        assert_eq!(contains("/tags", "/obj").unwrap(), json!(true));
+        assert_eq!(contains("/tags", "/word").unwrap(), json!(false));
+        assert_eq!(contains("/title", "/word").unwrap(), json!(true));
+        assert_eq!(contains("/title", "/other").unwrap(), json!(false));
+        assert_eq!(error_kind(contains("/n", "/word")), RuntimeErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    fn non_finite_arithmetic_is_an_error() {
+        let inputs = json!({ "neg": -8, "half": 0.5, "ten": 10, "big": 400, "huge": 1e308 });
+        let power = |base: &str, exponent: &str| run_op(json!({ "op": "power", "base": base, "exponent": exponent }), inputs.clone());
+        assert_eq!(error_kind(power("/neg", "/half")), RuntimeErrorKind::NonFinite);
+        assert_eq!(error_kind(power("/ten", "/big")), RuntimeErrorKind::NonFinite);
+        let overflow = run_op(json!({ "op": "multiply", "a": "/huge", "b": "/ten" }), inputs.clone());
+        assert_eq!(error_kind(overflow), RuntimeErrorKind::NonFinite);
+        assert_eq!(power("/ten", "/half").unwrap(), json!(10f64.sqrt()));
+    }
+
+    #[test]
+    fn sum_fields_totals_each_field_in_one_pass() {
+        let inputs = json!({ "projects": [
+            { "revenue": 1000, "costs": 300, "hours": 10 },
+            { "revenue": 500.5, "costs": "n/a", "hours": 4 },
+            { "revenue": 250 }
+        ]});
+        let totals = run_op(json!({ "op": "sum_fields", "list_path": "/projects", "fields": ["revenue", "costs", "hours"] }), inputs).unwrap();
+        assert_eq!(totals, json!({ "revenue": 1750.5, "costs": 300.0, "hours": 14.0 }));
+
+        let empty = run_op(json!({ "op": "sum_fields", "list_path": "/xs", "fields": ["a", "b"] }), json!({ "xs": [] })).unwrap();
+        assert_eq!(empty, json!({ "a": 0.0, "b": 0.0 }));
+    }
+
+    #[test]
+    fn output_strategies_differ_on_missing_properties() {
+        let outputs = json!({ "type": "object", "properties": { "profit": { "type": "number" }, "margin": { "type": "number" } } });
+        let partial = program(json!({}), outputs.clone(), json!([
+            { "id": "profit", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" }, "output_path": "/profit" }
+        ]));
+        let none_written = program(json!({}), outputs, json!([
+            { "id": "scratch", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/costs" }, "output_path": "/temp/x" }
+        ]));
+        let inputs = json!({ "revenue": 10, "costs": 4 });
+        let run = |program: &AppProgram, strategy| Runtime::execute_with_strategy(program, inputs.clone(), strategy);
+
+        let strict = run(&partial, OutputStrategy::SchemaStrict);
+        assert!(strict.as_ref().is_err_and(|e| e.to_string().contains("margin")), "{strict:?}");
+        assert_eq!(error_kind(strict), RuntimeErrorKind::PathNotFound);
+        assert_eq!(run(&partial, OutputStrategy::SchemaWithPointerFallback).unwrap(), json!({ "profit": 6.0 }));
+        assert_eq!(run(&partial, OutputStrategy::FullState).unwrap(), json!({ "profit": 6.0 }));
+
+        assert_eq!(run(&none_written, OutputStrategy::SchemaWithPointerFallback).unwrap(), json!({}));
+        let full = run(&none_written, OutputStrategy::FullState).unwrap();
+        assert_eq!((&full["inputs"]["revenue"], &full["temp"]["x"]), (&json!(10), &json!(6.0)));
+        assert_eq!(Runtime::execute(&none_written, inputs.clone()).unwrap(), full);
+    }
+
+    #[test]
+    fn execute_batch_keeps_order_and_per_input_errors() {
+        let results = Runtime::execute_batch(&profit_program(), vec![
+            json!({ "revenue": 10, "costs": 4 }),
+            json!({ "revenue": 10 }),
+            json!({ "revenue": 7.5, "costs": 0.5 }),
+        ]);
+        let [first, second, third] = &results[..] else { panic!("{results:?}") };
+        assert_eq!(first.as_ref().unwrap(), &json!({ "profit": 6.0 }));
+        assert!(second.is_err(), "{second:?}");
+        assert_eq!(third.as_ref().unwrap(), &json!({ "profit": 7.0 }));
+    }
+}
@@ -0,0 +1,104 @@
+use super::dsl::AppProgram;
+use super::runtime::path_covers;
+use std::fmt::Write;
+
+/// Render the program's dataflow as a GraphViz DOT digraph.
+///
+/// Each step is a node labelled with its id and op. An edge runs from a step
+/// to every later step that reads its `output_path`; input schema properties
+/// appear as source nodes feeding the steps that read them.
+pub fn program_to_dot(program: &AppProgram) -> String {
+    let mut dot = String::new();
+    let _ = writeln!(dot, "digraph \"{}\" {{", escape(&program.definition.name));
+    let _ = writeln!(dot, "    rankdir=LR;");
+
+    let input_keys: Vec<&str> = program.definition.input_schema
+        .get("properties")
+        .and_then(|v| v.as_object())
+        .map(|props| props.keys().map(String::as_str).collect())
+        .unwrap_or_default();
+
+    for key in &input_keys {
+        let _ = writeln!(dot, "    \"input:{0}\" [label=\"{0}\", shape=box];", escape(key));
+    }
+
+    for step in &program.steps {
+        let _ = writeln!(
+            dot,
+            "    \"{}\" [label=\"{}\\n{}\"];",
+            escape(&step.id),
+            escape(&step.id),
+            step.operation.name()
+        );
+    }
+
+    let mut edges: Vec<(String, &str)> = Vec::new();
+    for (i, step) in program.steps.iter().enumerate() {
+        for path in step.operation.read_paths() {
+            // The most recent earlier writer wins, mirroring execution order.
+            let producer = program.steps[..i].iter().rev().find(|s| path_covers(&s.output_path, path));
+            let source = match producer {
+                Some(p) => p.id.clone(),
+                None => match input_root(path).filter(|k| input_keys.contains(k)) {
+                    Some(key) => format!("input:{key}"),
+                    None => continue,
+                },
+            };
+            if !edges.iter().any(|(from, to)| *from == source && *to == step.id) {
+                edges.push((source, &step.id));
+            }
+        }
+    }
+
+    for (from, to) in edges {
+        let _ = writeln!(dot, "    \"{}\" -> \"{}\";", escape(&from), escape(to));
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+/// Top-level input key a path refers to, for both `/inputs/key/...` and `/key/...`.
+fn input_root(path: &str) -> Option<&str> {
+    let rel = path.strip_prefix("/inputs").unwrap_or(path);
+    rel.trim_start_matches('/').split('/').next().filter(|k| !k.is_empty())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dsl::AppDefinition;
+    use serde_json::json;
+
+    #[test]
+    fn dot_links_inputs_and_steps() {
+        let program = AppProgram {
+            definition: AppDefinition {
+                name: "Profit".into(),
+                description: String::new(),
+                input_schema: json!({ "properties": { "revenue": {}, "costs": {}, "unused": {} } }),
+                output_schema: json!({}),
+            },
+            steps: serde_json::from_value(json!([
+                { "id": "profit", "description": "", "operation": { "op": "subtract", "a": "/revenue", "b": "/inputs/costs" }, "output_path": "/temp/profit" },
+                { "id": "margin", "description": "", "operation": { "op": "ratio", "numerator": "/temp/profit", "denominator": "/revenue" }, "output_path": "/margin" }
+            ])).unwrap(),
+            examples: Vec::new(),
+        };
+
+        let dot = program_to_dot(&program);
+        assert!(dot.starts_with("digraph \"Profit\" {\n"));
+        assert!(dot.contains("\"profit\" [label=\"profit\\nsubtract\"];"));
+        let edges: Vec<&str> = dot.lines().filter(|l| l.contains("->")).map(str::trim).collect();
+        assert_eq!(edges, [
+            "\"input:revenue\" -> \"profit\";",
+            "\"input:costs\" -> \"profit\";",
+            "\"profit\" -> \"margin\";",
+            "\"input:revenue\" -> \"margin\";",
+        ]);
+    }
+}
@@ -8,12 +8,74 @@ pub enum MetaError {
     #[error("JSON Error: {0}")]
     JsonError(#[from] serde_json::Error),
     
-    #[error("Runtime Logic Error: {0}")]
-    RuntimeError(String),
+    #[error("Runtime Logic Error: {}", runtime_message(step_id.as_deref(), step_description.as_deref(), message))]
+    RuntimeError {
+        kind: RuntimeErrorKind,
+        path: Option<String>,
+        step_id: Option<String>,
+        step_description: Option<String>,
+        message: String,
+    },
     
     #[error("Generation Failed: {0}")]
     GenerationFailed(String),
     
     #[error("Validation Failed: {0}")]
     ValidationFailed(String),
-}
\ No newline at end of file
+
+    #[error("Configuration Error: {0}")]
+    ConfigError(String),
+
+    #[error("API Error {status}: {message}")]
+    HttpStatus { status: u16, message: String, retryable: bool },
+}
+
+/// What went wrong while interpreting a program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeErrorKind {
+    /// A read path resolved to nothing.
+    PathNotFound,
+    /// A write path has a structure the state can't hold.
+    InvalidPath,
+    /// A value had the wrong type (e.g. a string where a number was needed).
+    TypeMismatch,
+    DivisionByZero,
+    /// A `Lookup` key had no table entry and no default.
+    LookupMiss,
+    /// An operation's own parameters are inconsistent (e.g. inverted bounds).
+    InvalidArgument,
+    /// Arithmetic overflowed to infinity or produced NaN.
+    NonFinite,
+}
+
+impl MetaError {
+    /// A runtime error not tied to a particular path.
+    pub fn runtime(kind: RuntimeErrorKind, message: impl Into<String>) -> Self {
+        MetaError::RuntimeError { kind, path: None, step_id: None, step_description: None, message: message.into() }
+    }
+
+    /// A runtime error about the value at `path`.
+    pub fn runtime_at(kind: RuntimeErrorKind, path: &str, message: impl Into<String>) -> Self {
+        MetaError::RuntimeError { kind, path: Some(path.to_string()), step_id: None, step_description: None, message: message.into() }
+    }
+
+    /// Whether retrying the same request could succeed. Transport failures
+    /// (timeouts, dropped connections), retryable HTTP statuses and malformed
+    /// model output are; client errors, config and runtime errors are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            MetaError::HttpStatus { retryable, .. } => *retryable,
+            MetaError::ApiError(e) => !e.is_builder(),
+            MetaError::JsonError(_) | MetaError::GenerationFailed(_) => true,
+            MetaError::RuntimeError { .. } | MetaError::ValidationFailed(_) | MetaError::ConfigError(_) => false,
+        }
+    }
+}
+
+fn runtime_message(step_id: Option<&str>, step_description: Option<&str>, message: &str) -> String {
+    match (step_id, step_description) {
+        (Some(id), Some(description)) => format!("Step '{id}' ({description}): {message}"),
+        (Some(id), None) => format!("Step '{id}': {message}"),
+        (None, _) => message.to_string(),
+    }
+}
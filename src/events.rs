@@ -0,0 +1,47 @@
+use crate::core::dsl::{AppProgram, LogicStep};
+use crate::error::MetaError;
+use crate::orchestrator::TestResult;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Pipeline stage reported by `Listener::on_phase`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Phase {
+    Architecture,
+    Development,
+    /// Static dataflow check before QA (`OrchestratorConfig::static_validation`).
+    Validation,
+    Testing,
+}
+
+/// Structured progress callbacks for embedders. All methods default to no-ops,
+/// so implementors only override what they care about. The `log` output is
+/// emitted regardless of the listener.
+pub trait Listener: Send + Sync {
+    fn on_phase(&self, _phase: Phase) {}
+    fn on_step(&self, _step: &LogicStep, _value: &Value) {}
+    fn on_test_result(&self, _result: &TestResult) {}
+    /// Development produced the initial program (before any tests run).
+    fn on_steps_generated(&self, _program: &AppProgram) {}
+    /// Validation run `attempt` failed and the fixer is being called.
+    fn on_fixer_invoked(&self, _attempt: usize) {}
+}
+
+/// Listener that ignores every event.
+pub struct NoopListener;
+
+impl Listener for NoopListener {}
+
+/// Progress item yielded by `Orchestrator::build_application_stream`. The
+/// stream ends after `Done` or `Failed`.
+#[derive(Debug)]
+pub enum BuildEvent {
+    PhaseStarted(Phase),
+    /// Number of steps in the initial program.
+    StepsGenerated(usize),
+    TestResult(TestResult),
+    /// The fixer was called after validation run `attempt` failed.
+    FixerInvoked { attempt: usize },
+    Done(AppProgram),
+    Failed(MetaError),
+}
@@ -0,0 +1,34 @@
+pub mod error;
+pub mod events;
+pub mod core {
+    #[cfg(feature = "csv")]
+    pub mod csv_input;
+    #[cfg(feature = "chrono")]
+    pub mod dates;
+    pub mod dsl;
+    #[cfg(feature = "regex")]
+    pub mod patterns;
+    pub mod explain;
+    pub mod prune;
+    pub mod runtime;
+    pub mod viz;
+}
+pub mod ai {
+    pub mod client;
+    pub mod prompts;
+    pub mod agents;
+    pub mod schema_utils;
+    pub mod mock;
+}
+pub mod orchestrator;
+
+pub use ai::agents::{AgentSwarm, CostEstimate, FixAttempt, TestCase};
+pub use ai::client::{ClientObserver, GeminiClient, GeminiConfig, GenerationOutcome, LlmClient, NoopObserver, TokenUsage};
+#[cfg(any(test, feature = "mock"))]
+pub use ai::mock::MockClient;
+pub use ai::mock::StaticClient;
+pub use core::dsl::{AppDefinition, AppProgram, LogicOp, LogicStep, ProgramExample};
+pub use core::runtime::{ExecMetrics, OutputStrategy, OutputWarning, Runtime, StepError, StepTrace};
+pub use error::{MetaError, RuntimeErrorKind};
+pub use events::{BuildEvent, Listener, NoopListener, Phase};
+pub use orchestrator::{BuildReport, BuildStream, FallbackPolicy, Orchestrator, OrchestratorConfig, TestResult, TestSource};
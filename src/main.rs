@@ -1,65 +1,106 @@
-mod error;
-mod core {
-    pub mod dsl;
-    pub mod runtime;
-}
-mod ai {
-    pub mod client;
-    pub mod prompts;
-    pub mod agents;
-    pub mod schema_utils; // Registered here
+use clap::{Parser, Subcommand};
+use dotenv::dotenv;
+use meta_ai::{AppProgram, Orchestrator, Runtime, StaticClient};
+use serde_json::Value;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_PROMPT: &str = r#"
+    I need a financial tool for analysing project profitability.
+    Input:
+    - A list of 'projects'. Each project has 'name', 'revenue', 'costs', and 'hours_worked'.
+    - An 'overhead_rate' (hourly cost of overhead).
+
+    Output:
+    1. 'total_profit': Total Revenue - Total Costs - (Total Hours * Overhead Rate).
+    2. 'most_profitable_project': Name of the project with highest raw profit (Revenue - Costs).
+    3. 'profit_margin': Total Profit / Total Revenue (as a percentage).
+    4. 'summary': A text string summarizing the results.
+"#;
+
+#[derive(Parser)]
+#[command(name = "meta-ai", about = "Build, run and explain generated logic programs")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 }
-mod orchestrator;
 
-use dotenv::dotenv;
-use orchestrator::Orchestrator;
-use serde_json::json;
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a program from a natural-language request.
+    Build {
+        /// What the application should do. Defaults to the profitability demo.
+        #[arg(long)]
+        prompt: Option<String>,
+        /// Write the program JSON here instead of stdout.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Execute a saved program against an input document.
+    Run {
+        #[arg(long)]
+        program: PathBuf,
+        /// Input JSON file; reads stdin when omitted or `-`.
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Also write each step's value and timing here as JSON.
+        #[arg(long)]
+        trace: Option<PathBuf>,
+    },
+    /// Print a saved program as plain-English pseudocode.
+    Explain {
+        #[arg(long)]
+        program: PathBuf,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
     env_logger::builder().filter_level(log::LevelFilter::Info).init();
 
-    let orchestrator = Orchestrator::new();
-
-    let prompt = r#"
-        I need a financial tool for analysing project profitability.
-        Input: 
-        - A list of 'projects'. Each project has 'name', 'revenue', 'costs', and 'hours_worked'.
-        - An 'overhead_rate' (hourly cost of overhead).
-        
-        Output:
-        1. 'total_profit': Total Revenue - Total Costs - (Total Hours * Overhead Rate).
-        2. 'most_profitable_project': Name of the project with highest raw profit (Revenue - Costs).
-        3. 'profit_margin': Total Profit / Total Revenue (as a percentage).
-        4. 'summary': A text string summarizing the results.
-    "#;
-
-    println!("🤖 META-AI SYSTEM INITIALIZED");
-    println!("📝 Processing Request: \"{}\"\n", prompt.trim());
+    match Cli::parse().command {
+        Command::Build { prompt, out } => {
+            // Dry run: replay recorded `<stage>.json` responses instead of calling Gemini.
+            let orchestrator = match std::env::var("META_AI_FIXTURES") {
+                Ok(dir) => Orchestrator::with_client(Box::new(StaticClient::from_dir(dir)?)),
+                Err(_) => Orchestrator::new()?,
+            };
+            let prompt = prompt.as_deref().unwrap_or(DEFAULT_PROMPT);
+            log::info!("📝 Processing Request: \"{}\"", prompt.trim());
 
-    let app = orchestrator.build_application(prompt).await?;
-
-    println!("\n📦 PRODUCTION APP READY: {}", app.definition.name);
-    println!("--------------------------------------------------");
-
-    let real_data = json!({
-        "overhead_rate": 50.0,
-        "projects": [
-            { "name": "Website Redesign", "revenue": 15000, "costs": 2000, "hours_worked": 100 },
-            { "name": "Mobile App", "revenue": 40000, "costs": 15000, "hours_worked": 400 },
-            { "name": "Consulting", "revenue": 5000, "costs": 0, "hours_worked": 20 }
-        ]
-    });
-
-    match core::runtime::Runtime::execute(&app, real_data) {
-        Ok(output) => {
-            println!("{}", serde_json::to_string_pretty(&output)?);
+            let app = orchestrator.build_application(prompt).await?;
+            let json = serde_json::to_string_pretty(&app)?;
+            match out {
+                Some(path) => {
+                    std::fs::write(&path, json)?;
+                    log::info!("📦 {} written to {}", app.definition.name, path.display());
+                }
+                None => println!("{json}"),
+            }
         }
-        Err(e) => {
-            eprintln!("❌ Runtime Error in Production: {e}");
+        Command::Run { program, input, trace } => {
+            let app = load_program(&program)?;
+            let data: Value = match input.filter(|p| p.as_os_str() != "-") {
+                Some(path) => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+                None => {
+                    let mut text = String::new();
+                    std::io::stdin().read_to_string(&mut text)?;
+                    serde_json::from_str(&text)?
+                }
+            };
+            let output = match trace {
+                Some(path) => Runtime::execute_traced_to_file(&app, data, path)?,
+                None => Runtime::execute(&app, data)?,
+            };
+            println!("{}", serde_json::to_string_pretty(&output)?);
         }
+        Command::Explain { program } => println!("{}", load_program(&program)?.explain()),
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn load_program(path: &Path) -> Result<AppProgram, Box<dyn std::error::Error>> {
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
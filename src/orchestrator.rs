@@ -1,84 +1,476 @@
-use crate::ai::agents::AgentSwarm;
-use crate::core::dsl::AppProgram;
+use crate::ai::agents::{AgentSwarm, FixAttempt, TestCase};
+use crate::ai::client::{GeminiClient, GeminiConfig, LlmClient};
+use crate::ai::schema_utils::sample_from_schema;
+use crate::core::dsl::{AppDefinition, AppProgram, LogicStep, ProgramExample};
 use crate::core::runtime::Runtime;
 use crate::error::MetaError;
+use crate::events::{BuildEvent, Listener, NoopListener, Phase};
+use futures_core::Stream;
+use serde::Serialize;
 use serde_json::Value;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// Outcome of a single QA test case.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    pub output: Option<Value>,
+    pub error: Option<String>,
+}
+
+/// Everything `build_application_report` learned while building a program.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildReport {
+    pub program: AppProgram,
+    pub tests: Vec<TestCase>,
+    /// Validation runs performed (1 = passed without invoking the fixer, 0 = skipped).
+    pub attempts: usize,
+    /// Results from the final validation run.
+    pub test_results: Vec<TestResult>,
+    /// Every test passed. Only false for skipped validation or a
+    /// `FallbackPolicy::ReturnBestEffort` build that ran out of fixes.
+    pub fully_validated: bool,
+}
+
+/// What `build_*` does when the last validation run still has failures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FallbackPolicy {
+    /// Return `MetaError::ValidationFailed`.
+    #[default]
+    Fail,
+    /// Return the last program anyway, with `fully_validated: false` and the
+    /// failing run's `test_results` in the report.
+    ReturnBestEffort,
+}
+
+/// Where the validation loop's test cases come from.
+#[derive(Debug, Clone, Default)]
+pub enum TestSource {
+    /// Ask the QA agent (or fall back to a schema sample, per `qa_agent`).
+    #[default]
+    Generate,
+    /// Use only these cases; the QA agent is not called.
+    Provide(Vec<TestCase>),
+    /// Run these cases after the generated ones.
+    Both(Vec<TestCase>),
+}
+
+/// Retry budgets for the build pipeline. Defaults match the historical
+/// hard-coded value of 3 everywhere.
+#[derive(Debug, Clone)]
+pub struct OrchestratorConfig {
+    /// Attempts `write_logic` gets to return parseable steps.
+    pub logic_retries: usize,
+    /// Validation runs; the fixer is invoked between runs, so N runs allow N-1 fixes.
+    pub validation_retries: usize,
+    /// Attempts per LLM call inside `GeminiClient`.
+    pub client_retries: u32,
+    /// Stop a validation run at the first failing test. When false, every test
+    /// runs and all failures are reported to the fixer together.
+    pub fail_fast: bool,
+    /// Ask the QA agent for test cases. When false (or if the QA call fails)
+    /// a single test sampled from the input schema is used instead.
+    pub qa_agent: bool,
+    /// Return the program straight after Development, with no QA or fixer
+    /// calls. Meant for trusted programs where tests aren't needed.
+    pub skip_validation: bool,
+    /// Outcome when validation never passes.
+    pub fallback: FallbackPolicy,
+    /// Bytes of test input/output JSON kept in logs and fixer error reports.
+    pub log_preview_len: usize,
+    /// Run `Runtime::validate` after Development and send any dataflow
+    /// problems to the fixer before QA. Development and QA then run one
+    /// after the other instead of concurrently.
+    pub static_validation: bool,
+}
+
+impl Default for OrchestratorConfig {
+    fn default() -> Self {
+        Self {
+            logic_retries: 3,
+            validation_retries: 3,
+            client_retries: 3,
+            fail_fast: true,
+            qa_agent: true,
+            skip_validation: false,
+            fallback: FallbackPolicy::Fail,
+            log_preview_len: 300,
+            static_validation: false,
+        }
+    }
+}
 
 pub struct Orchestrator {
     swarm: AgentSwarm,
+    config: OrchestratorConfig,
+    listener: Arc<dyn Listener>,
 }
 
 impl Orchestrator {
-    pub fn new() -> Self {
-        Self { swarm: AgentSwarm::new() }
+    pub fn new() -> Result<Self, MetaError> {
+        Self::with_config(OrchestratorConfig::default())
+    }
+
+    pub fn with_config(config: OrchestratorConfig) -> Result<Self, MetaError> {
+        let client = GeminiClient::with_config(GeminiConfig {
+            max_retries: config.client_retries,
+            ..GeminiConfig::default()
+        })?;
+        Ok(Self::with_client_and_config(Box::new(client), config))
+    }
+
+    pub fn with_client(client: Box<dyn LlmClient>) -> Self {
+        Self::with_client_and_config(client, OrchestratorConfig::default())
+    }
+
+    /// Use a custom client. `client_retries` is not applied here; configure the client directly.
+    pub fn with_client_and_config(client: Box<dyn LlmClient>, config: OrchestratorConfig) -> Self {
+        let swarm = AgentSwarm::with_client(client).with_logic_retries(config.logic_retries);
+        Self { swarm, config, listener: Arc::new(NoopListener) }
+    }
+
+    /// Receive phase, step and test-result events while building.
+    pub fn with_listener(mut self, listener: Arc<dyn Listener>) -> Self {
+        self.listener = listener;
+        self
     }
 
     pub async fn build_application(&self, user_request: &str) -> Result<AppProgram, MetaError> {
+        self.build_application_report(user_request).await.map(|report| report.program)
+    }
+
+    /// Build an application and return the QA details alongside the program.
+    pub async fn build_application_report(&self, user_request: &str) -> Result<BuildReport, MetaError> {
+        self.build_with_tests(user_request, TestSource::Generate).await
+    }
+
+    /// Like `build_application_report`, validating against caller-supplied
+    /// test cases instead of (or as well as) the QA agent's.
+    pub async fn build_with_tests(&self, user_request: &str, source: TestSource) -> Result<BuildReport, MetaError> {
+        self.build(user_request, source, self.listener.as_ref()).await
+    }
+
+    /// Build an application, yielding progress events as they happen. The
+    /// build only advances while the stream is polled. Events also reach the
+    /// listener set with `with_listener`.
+    pub fn build_application_stream<'a>(&'a self, user_request: &'a str) -> BuildStream<'a> {
+        let events = Arc::new(Mutex::new(VecDeque::new()));
+        let listener = QueueListener { events: events.clone(), inner: self.listener.clone() };
+        let build = async move {
+            self.build(user_request, TestSource::Generate, &listener).await.map(|report| report.program)
+        };
+        BuildStream { build: Some(Box::pin(build)), events }
+    }
+
+    /// Static gate between Development and QA: feed `Runtime::validate`'s
+    /// dataflow problems to the fixer until the program is clean or the fix
+    /// budget (`validation_retries - 1`) is spent. Remaining problems are left
+    /// for the test runs to surface.
+    async fn repair_dataflow(&self, mut program: AppProgram, definition: &AppDefinition, listener: &dyn Listener) -> Result<AppProgram, MetaError> {
+        log::info!("🏗️  Static Validation");
+        listener.on_phase(Phase::Validation);
+        let mut history: Vec<FixAttempt> = Vec::new();
+        for attempt in 1..self.config.validation_retries.max(1) {
+            let Err(problems) = Runtime::validate(&program) else { break };
+            log::warn!("   🔧 {} dataflow problem(s); invoking Fixer Agent...", problems.len());
+            listener.on_fixer_invoked(attempt);
+            let error_report = format!("Static dataflow check failed:\n{}", problems.join("\n"));
+            let fixed = self.swarm.fix_program_with_history(&program, definition, &error_report, &history).await?;
+            history.push(FixAttempt { steps: std::mem::replace(&mut program, fixed).steps, error: error_report });
+            if history.len() > FIX_HISTORY_LEN {
+                history.remove(0);
+            }
+        }
+        match Runtime::validate(&program) {
+            Ok(()) => log::info!("   -> Dataflow check passed"),
+            Err(problems) => log::warn!("   Dataflow check still reports {} problem(s); continuing to tests", problems.len()),
+        }
+        Ok(program)
+    }
+
+    async fn build(&self, user_request: &str, source: TestSource, listener: &dyn Listener) -> Result<BuildReport, MetaError> {
         log::info!("🏗️  Phase 1: Architecture");
+        listener.on_phase(Phase::Architecture);
         let definition = self.swarm.define_app(user_request).await?;
         log::info!("   -> Defined: {}", definition.name);
 
         log::info!("🏗️  Phase 2: Development");
-        let mut program = self.swarm.write_logic(&definition).await?;
-        log::info!("   -> Generated {} steps of logic", program.steps.len());
+        listener.on_phase(Phase::Development);
+        if self.config.skip_validation {
+            let program = self.swarm.write_logic(&definition).await?;
+            log::info!("   -> Generated {} steps of logic (validation skipped)", program.steps.len());
+            listener.on_steps_generated(&program);
+            return Ok(BuildReport { program, tests: Vec::new(), attempts: 0, test_results: Vec::new(), fully_validated: false });
+        }
+
+        let qa = async {
+            let provided = match source {
+                TestSource::Provide(tests) => return tests,
+                TestSource::Both(tests) => tests,
+                TestSource::Generate => Vec::new(),
+            };
+            let mut tests = if self.config.qa_agent {
+                self.swarm.generate_tests(&definition).await.unwrap_or_else(|e| {
+                    log::warn!("   QA generation failed ({e}); falling back to a schema sample");
+                    vec![sample_test(&definition)]
+                })
+            } else {
+                vec![sample_test(&definition)]
+            };
+            tests.extend(provided);
+            tests
+        };
+        let (mut program, tests) = if self.config.static_validation {
+            let program = self.swarm.write_logic(&definition).await?;
+            log::info!("   -> Generated {} steps of logic", program.steps.len());
+            listener.on_steps_generated(&program);
+            let program = self.repair_dataflow(program, &definition, listener).await?;
+
+            log::info!("🏗️  Phase 3: QA & Testing");
+            listener.on_phase(Phase::Testing);
+            let tests = qa.await;
+            log::info!("   -> Generated {} test cases", tests.len());
+            (program, tests)
+        } else {
+            // Test generation depends only on the definition, so Development and
+            // QA run concurrently. A Development failure is reported first, and
+            // the Testing phase starts only once both have finished.
+            let (program, tests) = tokio::join!(self.swarm.write_logic(&definition), qa);
+            let program = program?;
+            log::info!("   -> Generated {} steps of logic and {} test cases", program.steps.len(), tests.len());
+            listener.on_steps_generated(&program);
+            log::info!("🏗️  Phase 3: QA & Testing");
+            listener.on_phase(Phase::Testing);
+            (program, tests)
+        };
 
-        log::info!("🏗️  Phase 3: QA & Testing");
-        let tests = self.swarm.generate_tests(&definition).await?;
-        
         // Validation Loop
-        let max_retries = 3;
+        let mut history: Vec<FixAttempt> = Vec::new();
+        let max_retries = self.config.validation_retries.max(1);
+        let mut test_results = Vec::new();
         for attempt in 1..=max_retries {
             log::info!("   🛡️  Validation Run #{attempt}...");
             
-            let mut all_passed = true;
-            let mut error_report = String::new();
+            test_results.clear();
+            let mut failures = Vec::new();
 
             for test in &tests {
-                // ROBUSTNESS: Handle case where LLM returns input as a stringified JSON string
-                let input_val = if let Some(input_str) = test.input.as_str() {
-                    match serde_json::from_str::<Value>(input_str) {
-                        Ok(v) => v,
-                        Err(_) => test.input.clone(),
-                    }
-                } else {
-                    test.input.clone()
-                };
-
-                match Runtime::execute(&program, input_val.clone()) {
-                    Ok(output) => {
-                        log::info!("      ✅ Test '{}' Passed", test.name);
-                        log::info!("         Input:  {}", truncate_json(&input_val));
-                        log::info!("         Output: {}", truncate_json(&output));
-                    },
-                    Err(e) => {
-                        log::error!("      ❌ Test '{}' Failed: {}", test.name, e);
-                        all_passed = false;
-                        error_report = format!("Test '{}' failed: {}", test.name, e);
-                        break; // Stop testing, go to fix
-                    }
+                let result = run_test(&program, test, listener, self.config.log_preview_len);
+                listener.on_test_result(&result);
+                let passed = result.passed;
+                if let Some(err) = &result.error {
+                    failures.push(format!("Test '{}' failed: {}", test.name, err));
+                }
+                test_results.push(result);
+                if !passed && self.config.fail_fast {
+                    break; // Stop testing, go to fix
                 }
             }
 
-            if all_passed {
+            if failures.is_empty() {
                 log::info!("🎉 Program Verified Successfully!");
-                return Ok(program);
+                program.examples = tests.iter().zip(&test_results)
+                    .filter_map(|(test, result)| Some(ProgramExample { input: test_input(test), output: result.output.clone()? }))
+                    .collect();
+                return Ok(BuildReport { program, tests, attempts: attempt, test_results, fully_validated: true });
             }
 
             if attempt < max_retries {
                 log::warn!("   🔧 Invoking Fixer Agent...");
-                program = self.swarm.fix_program(&program, &definition, &error_report).await?;
+                listener.on_fixer_invoked(attempt);
+                let error_report = failures.join("\n");
+                let fixed = self.swarm.fix_program_with_history(&program, &definition, &error_report, &history).await?;
+                history.push(FixAttempt { steps: std::mem::replace(&mut program, fixed).steps, error: error_report });
+                if history.len() > FIX_HISTORY_LEN {
+                    history.remove(0);
+                }
             }
         }
 
-        Err(MetaError::ValidationFailed("Failed to generate valid program after max retries".into()))
+        match self.config.fallback {
+            FallbackPolicy::Fail => {
+                Err(MetaError::ValidationFailed("Failed to generate valid program after max retries".into()))
+            }
+            FallbackPolicy::ReturnBestEffort => {
+                log::warn!("⚠️  Validation never fully passed; returning the last program");
+                Ok(BuildReport { program, tests, attempts: max_retries, test_results, fully_validated: false })
+            }
+        }
     }
 }
 
-fn truncate_json(v: &Value) -> String {
-    let s = serde_json::to_string(v).unwrap_or_default();
-    if s.len() > 300 {
-        format!("{}... (len: {})", &s[..300], s.len())
+type BuildFuture<'a> = Pin<Box<dyn Future<Output = Result<AppProgram, MetaError>> + Send + 'a>>;
+
+/// Stream of `BuildEvent`s returned by `Orchestrator::build_application_stream`.
+pub struct BuildStream<'a> {
+    build: Option<BuildFuture<'a>>,
+    events: Arc<Mutex<VecDeque<BuildEvent>>>,
+}
+
+impl Stream for BuildStream<'_> {
+    type Item = BuildEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<BuildEvent>> {
+        if let Some(event) = self.events.lock().unwrap().pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        let Some(build) = self.build.as_mut() else { return Poll::Ready(None) };
+        match build.as_mut().poll(cx) {
+            // Events queued during this poll are handed out first; the build
+            // is polled again on the next call.
+            Poll::Pending => match self.events.lock().unwrap().pop_front() {
+                Some(event) => Poll::Ready(Some(event)),
+                None => Poll::Pending,
+            },
+            Poll::Ready(result) => {
+                self.build = None;
+                let done = match result {
+                    Ok(program) => BuildEvent::Done(program),
+                    Err(e) => BuildEvent::Failed(e),
+                };
+                // Events from the final poll come before `done`.
+                let mut events = self.events.lock().unwrap();
+                events.push_back(done);
+                Poll::Ready(events.pop_front())
+            }
+        }
+    }
+}
+
+/// Queues events for a `BuildStream` and forwards them to the orchestrator's listener.
+struct QueueListener {
+    events: Arc<Mutex<VecDeque<BuildEvent>>>,
+    inner: Arc<dyn Listener>,
+}
+
+impl QueueListener {
+    fn push(&self, event: BuildEvent) {
+        self.events.lock().unwrap().push_back(event);
+    }
+}
+
+impl Listener for QueueListener {
+    fn on_phase(&self, phase: Phase) {
+        self.inner.on_phase(phase);
+        self.push(BuildEvent::PhaseStarted(phase));
+    }
+
+    fn on_step(&self, step: &LogicStep, value: &Value) {
+        self.inner.on_step(step, value);
+    }
+
+    fn on_test_result(&self, result: &TestResult) {
+        self.inner.on_test_result(result);
+        self.push(BuildEvent::TestResult(result.clone()));
+    }
+
+    fn on_steps_generated(&self, program: &AppProgram) {
+        self.inner.on_steps_generated(program);
+        self.push(BuildEvent::StepsGenerated(program.steps.len()));
+    }
+
+    fn on_fixer_invoked(&self, attempt: usize) {
+        self.inner.on_fixer_invoked(attempt);
+        self.push(BuildEvent::FixerInvoked { attempt });
+    }
+}
+
+/// Failed attempts (most recent last) shown to the fixer alongside the current one.
+const FIX_HISTORY_LEN: usize = 3;
+
+/// Zero-cost test case: a minimal input synthesized from the input schema,
+/// checked only for running without error.
+fn sample_test(definition: &AppDefinition) -> TestCase {
+    TestCase {
+        name: "Schema sample".to_string(),
+        input: sample_from_schema(&definition.input_schema),
+        expected_output_keys: Vec::new(),
+    }
+}
+
+/// Run a single QA test case against `program`, checking its expected output keys.
+fn run_test(program: &AppProgram, test: &TestCase, listener: &dyn Listener, preview_len: usize) -> TestResult {
+    let input_val = test_input(test);
+
+    match Runtime::execute_with_listener(program, input_val.clone(), listener) {
+        Ok(output) => {
+            let missing: Vec<&String> = test.expected_output_keys.iter()
+                .filter(|key| output.get(key.as_str()).is_none())
+                .collect();
+
+            if !missing.is_empty() {
+                log::error!("      ❌ Test '{}' Failed: missing output keys {:?}", test.name, missing);
+                let mut error = format!(
+                    "output is missing expected keys {:?}. Output was: {}",
+                    missing, truncate_json(&output, preview_len)
+                );
+                for warning in Runtime::output_warnings(program, &output) {
+                    error.push_str(&format!("\n  - {}", warning.message));
+                }
+                return TestResult { name: test.name.clone(), passed: false, output: Some(output), error: Some(error) };
+            }
+
+            log::info!("      ✅ Test '{}' Passed", test.name);
+            log::info!("         Input:  {}", truncate_json(&input_val, preview_len));
+            log::info!("         Output: {}", truncate_json(&output, preview_len));
+            TestResult { name: test.name.clone(), passed: true, output: Some(output), error: None }
+        },
+        Err(e) => {
+            log::error!("      ❌ Test '{}' Failed: {}", test.name, e);
+            TestResult { name: test.name.clone(), passed: false, output: None, error: Some(e.to_string()) }
+        }
+    }
+}
+
+fn test_input(test: &TestCase) -> Value {
+    // ROBUSTNESS: Handle case where LLM returns input as a stringified JSON string
+    if let Some(input_str) = test.input.as_str() {
+        match serde_json::from_str::<Value>(input_str) {
+            Ok(v) => v,
+            Err(_) => test.input.clone(),
+        }
     } else {
-        s
+        test.input.clone()
+    }
+}
+
+/// Compact JSON cut to at most `limit` bytes, backing off to a character
+/// boundary so multi-byte text can't split mid-character.
+fn truncate_json(v: &Value, limit: usize) -> String {
+    let s = serde_json::to_string(v).unwrap_or_default();
+    if s.len() <= limit {
+        return s;
+    }
+    let mut end = limit;
+    while !s.is_char_boundary(end) {
+        end -= 1;
     }
-}
\ No newline at end of file
+    format!("{}... (len: {})", &s[..end], s.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn truncate_json_backs_off_to_a_char_boundary() {
+        // After the opening quote every character takes 3 bytes, so most of
+        // these limits fall in the middle of one.
+        let value = json!("€".repeat(200));
+        let full = serde_json::to_string(&value).unwrap();
+        for limit in 298..=302 {
+            let cut = truncate_json(&value, limit);
+            let (kept, suffix) = cut.split_once("...").unwrap();
+            assert!(kept.len() <= limit && kept.len() > limit - 3, "{limit}: {}", kept.len());
+            assert!(full.starts_with(kept));
+            assert_eq!(suffix, format!(" (len: {})", full.len()));
+        }
+        assert_eq!(truncate_json(&json!({ "a": 1 }), 300), r#"{"a":1}"#);
+    }
+}
@@ -0,0 +1,42 @@
+use serde_json::{json, Value};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn fixture(name: &str) -> String {
+    format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+fn meta_ai() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_meta-ai"))
+}
+
+fn assert_profit_output(stdout: &[u8]) {
+    let output: Value = serde_json::from_slice(stdout).unwrap();
+    assert_eq!(output["total_profit"], json!(17000.0));
+    assert_eq!(output["most_profitable_project"], "Mobile App");
+}
+
+#[test]
+fn run_executes_a_saved_program_on_an_input_file() {
+    let out = meta_ai()
+        .args(["run", "--program", &fixture("profit-program.json"), "--input", &fixture("profit-input.json")])
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert_profit_output(&out.stdout);
+}
+
+#[test]
+fn run_reads_input_from_stdin() {
+    let mut child = meta_ai()
+        .args(["run", "--program", &fixture("profit-program.json")])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input = std::fs::read(fixture("profit-input.json")).unwrap();
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+    assert_profit_output(&out.stdout);
+}
@@ -0,0 +1,406 @@
+use futures_core::Stream;
+use meta_ai::{BuildEvent, FallbackPolicy, Listener, MetaError, MockClient, Orchestrator, OrchestratorConfig, Phase, Runtime, StaticClient, TestCase, TestSource};
+use serde_json::json;
+use std::future::poll_fn;
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+
+fn fixture(stage: &str) -> String {
+    let path = format!("{}/fixtures/profit/{stage}.json", env!("CARGO_MANIFEST_DIR"));
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"))
+}
+
+/// Mock serving the recorded profitability-app responses for every stage.
+fn profit_client() -> MockClient {
+    MockClient::new()
+        .with_response("Architecture", fixture("Architecture"))
+        .with_response("Development", fixture("Development"))
+        .with_response("QA", fixture("QA"))
+}
+
+#[tokio::test]
+async fn builds_offline_with_mock_client() {
+    let client = Arc::new(profit_client());
+    let program = Orchestrator::with_client(Box::new(client.clone()))
+        .build_application("project profitability tool")
+        .await
+        .unwrap();
+
+    assert_eq!(program.definition.name, "Project Profitability Analyzer");
+    assert!(!program.steps.is_empty());
+    assert_eq!(client.call_count("Architecture"), 1);
+    assert_eq!(client.call_count("Development"), 1);
+    assert_eq!(client.call_count("QA"), 1);
+    assert_eq!(client.call_count("Fixer"), 0);
+}
+
+/// Steps that only ever write `total_profit`.
+const PARTIAL_STEPS: &str = r#"[
+  {"id": "total", "description": "Total revenue", "operation": {"op": "sum", "list_path": "/projects", "field": "revenue"}, "output_path": "/total_profit"}
+]"#;
+
+#[tokio::test]
+async fn missing_expected_keys_invoke_the_fixer() {
+    let client = Arc::new(
+        MockClient::new()
+            .with_response("Architecture", fixture("Architecture"))
+            .with_response("Development", PARTIAL_STEPS)
+            .with_response("QA", fixture("QA"))
+            .with_response("Fixer", fixture("Development")),
+    );
+    let report = Orchestrator::with_client(Box::new(client.clone()))
+        .build_application_report("project profitability tool")
+        .await
+        .unwrap();
+
+    assert_eq!(client.call_count("Fixer"), 1);
+    let fixer = client.calls().into_iter().find(|c| c.stage == "Fixer").unwrap();
+    assert!(fixer.user_prompt.contains("missing expected keys"), "{}", fixer.user_prompt);
+    assert!(fixer.user_prompt.contains("most_profitable_project"));
+    assert_eq!(report.attempts, 2);
+    assert!(report.fully_validated);
+}
+
+/// Two cases against the profit inputs: one only needs `total_profit`, the
+/// other also needs `summary`.
+const PASS_AND_FAIL_TESTS: &str = r#"[
+  {"name": "Total only", "input": {"overhead_rate": 1, "projects": [{"name": "A", "revenue": 10, "costs": 1, "hours_worked": 1}]}, "expected_output_keys": ["total_profit"]},
+  {"name": "Needs summary", "input": {"overhead_rate": 1, "projects": []}, "expected_output_keys": ["total_profit", "summary"]}
+]"#;
+
+#[tokio::test]
+async fn report_captures_passing_and_failing_tests() {
+    let client = MockClient::new()
+        .with_response("Architecture", fixture("Architecture"))
+        .with_response("Development", PARTIAL_STEPS)
+        .with_response("QA", PASS_AND_FAIL_TESTS);
+    let config = OrchestratorConfig {
+        validation_retries: 1,
+        fail_fast: false,
+        fallback: FallbackPolicy::ReturnBestEffort,
+        ..OrchestratorConfig::default()
+    };
+    let report = Orchestrator::with_client_and_config(Box::new(client), config)
+        .build_application_report("project profitability tool")
+        .await
+        .unwrap();
+
+    assert_eq!(report.tests.len(), 2);
+    let [pass, fail] = &report.test_results[..] else { panic!("{:?}", report.test_results) };
+    assert_eq!((pass.name.as_str(), pass.passed), ("Total only", true));
+    assert_eq!(pass.output.as_ref().unwrap()["total_profit"], 10.0);
+    assert!(pass.error.is_none());
+    assert_eq!((fail.name.as_str(), fail.passed), ("Needs summary", false));
+    assert!(fail.error.as_deref().unwrap().contains("summary"));
+}
+
+/// Mock whose programs never pass the recorded QA cases, even after fixes.
+fn never_passing_client() -> MockClient {
+    MockClient::new()
+        .with_response("Architecture", fixture("Architecture"))
+        .with_response("Development", PARTIAL_STEPS)
+        .with_response("QA", fixture("QA"))
+        .with_response("Fixer", PARTIAL_STEPS)
+}
+
+#[tokio::test]
+async fn validation_retries_bound_the_fixer_calls() {
+    // N validation runs leave room for N - 1 fixes.
+    for (validation_retries, fixes) in [(1, 0), (2, 1)] {
+        let client = Arc::new(never_passing_client());
+        let config = OrchestratorConfig { validation_retries, ..OrchestratorConfig::default() };
+        let result = Orchestrator::with_client_and_config(Box::new(client.clone()), config)
+            .build_application("project profitability tool")
+            .await;
+
+        assert!(matches!(result, Err(MetaError::ValidationFailed(_))), "{validation_retries}: {result:?}");
+        assert_eq!(client.call_count("Fixer"), fixes, "validation_retries = {validation_retries}");
+    }
+}
+
+#[tokio::test]
+async fn logic_retries_bound_development_calls() {
+    let client = Arc::new(
+        MockClient::new()
+            .with_response("Architecture", fixture("Architecture"))
+            .with_response("Development", "not json")
+            .with_response("QA", fixture("QA")),
+    );
+    let config = OrchestratorConfig { logic_retries: 2, ..OrchestratorConfig::default() };
+    let result = Orchestrator::with_client_and_config(Box::new(client.clone()), config)
+        .build_application("project profitability tool")
+        .await;
+
+    assert!(matches!(result, Err(MetaError::ValidationFailed(_))), "{result:?}");
+    assert_eq!(client.call_count("Development"), 2);
+}
+
+#[tokio::test]
+async fn all_failures_reach_the_fixer_without_fail_fast() {
+    let prompt_for = |fail_fast| async move {
+        let client = Arc::new(never_passing_client());
+        let config = OrchestratorConfig { validation_retries: 2, fail_fast, ..OrchestratorConfig::default() };
+        let _ = Orchestrator::with_client_and_config(Box::new(client.clone()), config)
+            .build_application("project profitability tool")
+            .await;
+        client.calls().into_iter().find(|c| c.stage == "Fixer").unwrap().user_prompt
+    };
+
+    let all = prompt_for(false).await;
+    assert!(all.contains("Test 'Happy path' failed"), "{all}");
+    assert!(all.contains("Test 'Zero revenue' failed"), "{all}");
+
+    let first_only = prompt_for(true).await;
+    assert!(first_only.contains("Test 'Happy path' failed"));
+    assert!(!first_only.contains("Test 'Zero revenue' failed"));
+}
+
+#[derive(Default)]
+struct PhaseRecorder(Mutex<Vec<Phase>>);
+
+impl Listener for PhaseRecorder {
+    fn on_phase(&self, phase: Phase) {
+        self.0.lock().unwrap().push(phase);
+    }
+}
+
+#[tokio::test]
+async fn listener_observes_each_phase_in_order() {
+    let recorder = Arc::new(PhaseRecorder::default());
+    Orchestrator::with_client(Box::new(profit_client()))
+        .with_listener(recorder.clone())
+        .build_application("project profitability tool")
+        .await
+        .unwrap();
+
+    assert_eq!(*recorder.0.lock().unwrap(), [Phase::Architecture, Phase::Development, Phase::Testing]);
+}
+
+#[tokio::test]
+async fn concurrent_development_and_qa_both_land_in_the_report() {
+    let report = Orchestrator::with_client(Box::new(profit_client()))
+        .build_application_report("project profitability tool")
+        .await
+        .unwrap();
+
+    assert_eq!(report.program.steps.len(), 11);
+    let names: Vec<&str> = report.tests.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, ["Happy path", "Zero revenue", "Empty projects"]);
+    assert!(report.test_results.iter().all(|r| r.passed), "{:?}", report.test_results);
+}
+
+#[tokio::test]
+async fn development_failure_is_reported_alongside_a_successful_qa() {
+    let client = Arc::new(
+        MockClient::new()
+            .with_response("Architecture", fixture("Architecture"))
+            .with_response("Development", "not json")
+            .with_response("QA", fixture("QA")),
+    );
+    let config = OrchestratorConfig { logic_retries: 1, ..OrchestratorConfig::default() };
+    let result = Orchestrator::with_client_and_config(Box::new(client.clone()), config)
+        .build_application("project profitability tool")
+        .await;
+
+    assert!(matches!(result, Err(MetaError::ValidationFailed(_))), "{result:?}");
+    assert_eq!(client.call_count("QA"), 1);
+    assert_eq!(client.call_count("Fixer"), 0);
+}
+
+#[tokio::test]
+async fn static_client_builds_from_fixtures_and_runs_on_real_data() {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let client = StaticClient::from_dir(format!("{root}/fixtures/profit")).unwrap();
+    let program = Orchestrator::with_client(Box::new(client))
+        .build_application("project profitability tool")
+        .await
+        .unwrap();
+
+    let inputs = serde_json::from_str(&std::fs::read_to_string(format!("{root}/fixtures/profit-input.json")).unwrap()).unwrap();
+    let output = Runtime::execute(&program, inputs).unwrap();
+    assert_eq!(output["total_profit"], json!(17000.0));
+    assert_eq!(output["most_profitable_project"], "Mobile App");
+    assert!((output["profit_margin"].as_f64().unwrap() - 28.333).abs() < 1e-3);
+    assert_eq!(output["summary"], "Total profit $17,000.00 at a 28.3% margin; best project: Mobile App.");
+}
+
+#[tokio::test]
+async fn skip_validation_makes_no_qa_or_fixer_calls() {
+    let client = Arc::new(profit_client());
+    let config = OrchestratorConfig { skip_validation: true, ..OrchestratorConfig::default() };
+    let report = Orchestrator::with_client_and_config(Box::new(client.clone()), config)
+        .build_application_report("project profitability tool")
+        .await
+        .unwrap();
+
+    assert_eq!(report.program.steps.len(), 11);
+    assert_eq!((report.attempts, report.fully_validated), (0, false));
+    assert!(report.tests.is_empty());
+    assert_eq!(client.call_count("Development"), 1);
+    assert_eq!(client.call_count("QA"), 0);
+    assert_eq!(client.call_count("Fixer"), 0);
+}
+
+#[tokio::test]
+async fn fixer_sees_the_previous_attempts_error() {
+    let client = Arc::new(never_passing_client());
+    let config = OrchestratorConfig { validation_retries: 3, ..OrchestratorConfig::default() };
+    let _ = Orchestrator::with_client_and_config(Box::new(client.clone()), config)
+        .build_application("project profitability tool")
+        .await;
+
+    let prompts: Vec<String> = client.calls().into_iter().filter(|c| c.stage == "Fixer").map(|c| c.user_prompt).collect();
+    let [first, second] = &prompts[..] else { panic!("expected two fixer calls, got {}", prompts.len()) };
+    assert!(!first.contains("PREVIOUS ATTEMPTS"), "{first}");
+    let history = &second[second.find("PREVIOUS ATTEMPTS").expect(second)..second.find("Current Steps:").unwrap()];
+    assert!(history.contains("Attempt 1: "), "{history}");
+    assert!(history.contains("Error: ") && history.contains("Test 'Happy path' failed"), "{history}");
+}
+
+/// A caller-supplied case the recorded program can never pass.
+fn regression_case() -> TestCase {
+    TestCase {
+        name: "Regression".into(),
+        input: serde_json::from_str(&std::fs::read_to_string(format!("{}/fixtures/profit-input.json", env!("CARGO_MANIFEST_DIR"))).unwrap()).unwrap(),
+        expected_output_keys: vec!["total_profit".into(), "net_margin".into()],
+    }
+}
+
+#[tokio::test]
+async fn provided_failing_test_triggers_the_fixer() {
+    let client = Arc::new(profit_client().with_response("Fixer", fixture("Development")));
+    let config = OrchestratorConfig { validation_retries: 2, fallback: FallbackPolicy::ReturnBestEffort, ..OrchestratorConfig::default() };
+    let report = Orchestrator::with_client_and_config(Box::new(client.clone()), config)
+        .build_with_tests("project profitability tool", TestSource::Provide(vec![regression_case()]))
+        .await
+        .unwrap();
+
+    assert_eq!(client.call_count("QA"), 0);
+    assert_eq!(client.call_count("Fixer"), 1);
+    let fixer = client.calls().into_iter().find(|c| c.stage == "Fixer").unwrap();
+    assert!(fixer.user_prompt.contains("Test 'Regression' failed"), "{}", fixer.user_prompt);
+    assert!(fixer.user_prompt.contains("net_margin"));
+    assert!(!report.fully_validated);
+}
+
+#[tokio::test]
+async fn provided_tests_run_after_the_generated_ones() {
+    let client = Arc::new(profit_client());
+    let config = OrchestratorConfig { validation_retries: 1, fallback: FallbackPolicy::ReturnBestEffort, ..OrchestratorConfig::default() };
+    let report = Orchestrator::with_client_and_config(Box::new(client.clone()), config)
+        .build_with_tests("project profitability tool", TestSource::Both(vec![regression_case()]))
+        .await
+        .unwrap();
+
+    assert_eq!(client.call_count("QA"), 1);
+    let names: Vec<&str> = report.tests.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, ["Happy path", "Zero revenue", "Empty projects", "Regression"]);
+}
+
+#[tokio::test]
+async fn built_program_carries_passing_examples() {
+    let report = Orchestrator::with_client(Box::new(profit_client()))
+        .build_application_report("project profitability tool")
+        .await
+        .unwrap();
+
+    let examples = &report.program.examples;
+    assert_eq!(examples.len(), report.tests.len());
+    for (example, test) in examples.iter().zip(&report.tests) {
+        assert_eq!(example.input, test.input);
+        assert_eq!(example.output, Runtime::execute(&report.program, test.input.clone()).unwrap());
+    }
+}
+
+/// Drain a build stream into one line per event.
+async fn stream_events(orchestrator: &Orchestrator) -> Vec<String> {
+    let mut stream = pin!(orchestrator.build_application_stream("project profitability tool"));
+    let mut events = Vec::new();
+    while let Some(event) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+        events.push(match event {
+            BuildEvent::PhaseStarted(phase) => format!("phase {phase:?}"),
+            BuildEvent::StepsGenerated(n) => format!("steps {n}"),
+            BuildEvent::TestResult(r) => format!("test {} {}", r.name, if r.passed { "passed" } else { "failed" }),
+            BuildEvent::FixerInvoked { attempt } => format!("fixer {attempt}"),
+            BuildEvent::Done(program) => format!("done {}", program.definition.name),
+            BuildEvent::Failed(e) => format!("failed {}", e.to_string().split(':').next().unwrap()),
+        });
+    }
+    events
+}
+
+#[tokio::test]
+async fn stream_reports_each_build_event_in_order() {
+    let events = stream_events(&Orchestrator::with_client(Box::new(profit_client()))).await;
+    assert_eq!(events, [
+        "phase Architecture",
+        "phase Development",
+        "steps 11",
+        "phase Testing",
+        "test Happy path passed",
+        "test Zero revenue passed",
+        "test Empty projects passed",
+        "done Project Profitability Analyzer",
+    ]);
+
+    let config = OrchestratorConfig { validation_retries: 2, ..OrchestratorConfig::default() };
+    let events = stream_events(&Orchestrator::with_client_and_config(Box::new(never_passing_client()), config)).await;
+    assert_eq!(events, [
+        "phase Architecture",
+        "phase Development",
+        "steps 1",
+        "phase Testing",
+        "test Happy path failed",
+        "fixer 1",
+        "test Happy path failed",
+        "failed Validation Failed",
+    ]);
+}
+
+#[tokio::test]
+async fn best_effort_returns_the_last_program_when_validation_never_passes() {
+    let client = Arc::new(never_passing_client());
+    let config = OrchestratorConfig { validation_retries: 2, fallback: FallbackPolicy::ReturnBestEffort, ..OrchestratorConfig::default() };
+    let report = Orchestrator::with_client_and_config(Box::new(client.clone()), config)
+        .build_application_report("project profitability tool")
+        .await
+        .unwrap();
+
+    assert!(!report.fully_validated);
+    assert_eq!(client.call_count("Fixer"), 1);
+    assert_eq!(report.attempts, 2);
+    let ids: Vec<&str> = report.program.steps.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, ["total"]);
+    assert!(report.test_results.iter().any(|r| !r.passed));
+}
+
+/// Reads `/temp/total` one step before it is written.
+const FORWARD_REFERENCE_STEPS: &str = r#"[
+  {"id": "profit", "description": "Profit", "operation": {"op": "get", "path": "/temp/total"}, "output_path": "/total_profit"},
+  {"id": "total", "description": "Total revenue", "operation": {"op": "sum", "list_path": "/projects", "field": "revenue"}, "output_path": "/temp/total"}
+]"#;
+
+#[tokio::test]
+async fn static_validation_repairs_forward_references_before_qa() {
+    let client = Arc::new(
+        MockClient::new()
+            .with_response("Architecture", fixture("Architecture"))
+            .with_response("Development", FORWARD_REFERENCE_STEPS)
+            .with_response("Fixer", fixture("Development"))
+            .with_response("QA", fixture("QA")),
+    );
+    let recorder = Arc::new(PhaseRecorder::default());
+    let config = OrchestratorConfig { static_validation: true, ..OrchestratorConfig::default() };
+    let report = Orchestrator::with_client_and_config(Box::new(client.clone()), config)
+        .with_listener(recorder.clone())
+        .build_application_report("project profitability tool")
+        .await
+        .unwrap();
+
+    let stages: Vec<String> = client.calls().into_iter().map(|c| c.stage).collect();
+    assert_eq!(stages, ["Architecture", "Development", "Fixer", "QA"]);
+    let fixer = client.calls().into_iter().find(|c| c.stage == "Fixer").unwrap();
+    assert!(fixer.user_prompt.contains("/temp/total"), "{}", fixer.user_prompt);
+    assert_eq!(*recorder.0.lock().unwrap(), [Phase::Architecture, Phase::Development, Phase::Validation, Phase::Testing]);
+    assert!(report.fully_validated);
+}